@@ -3,22 +3,28 @@
 
 use panic_halt as _;
 
-#[rtic::app(device = rp_pico::hal::pac, peripherals = true)]
+#[rtic::app(device = rp_pico::hal::pac, peripherals = true, dispatchers = [TIMER_IRQ_1])]
 mod app {
 
     use embedded_hal::digital::v2::OutputPin;
-    use embedded_time::duration::Extensions;
     use rp_pico::{
-        hal::{self, clocks::init_clocks_and_plls, timer::Alarm, watchdog::Watchdog, Sio},
+        hal::{
+            self,
+            clocks::init_clocks_and_plls,
+            timer::{monotonic::Monotonic, Alarm0, Duration},
+            watchdog::Watchdog,
+            Sio,
+        },
         XOSC_CRYSTAL_FREQ,
     };
 
-    const SCAN_TIME_US: u32 = 1000000;
+    const SCAN_TIME: Duration = Duration::secs(1);
+
+    #[monotonic(binds = TIMER_IRQ_0, default = true)]
+    type Mono = Monotonic<Alarm0>;
 
     #[shared]
     struct Shared {
-        timer: hal::Timer,
-        alarm: hal::timer::Alarm0,
         led: hal::gpio::Pin<hal::gpio::pin::bank0::Gpio25, hal::gpio::PushPullOutput>,
     }
 
@@ -57,20 +63,22 @@ mod app {
         led.set_low().unwrap();
 
         let mut timer = hal::Timer::new(c.device.TIMER, &mut resets);
-        let mut alarm = timer.alarm_0().unwrap();
-        let _ = alarm.schedule(SCAN_TIME_US.microseconds());
-        alarm.enable_interrupt();
+        let alarm = timer.alarm_0().unwrap();
+        blink::spawn_after(SCAN_TIME).unwrap();
 
-        (Shared { timer, alarm, led }, Local {}, init::Monotonics())
+        (
+            Shared { led },
+            Local {},
+            init::Monotonics(Monotonic::new(timer, alarm)),
+        )
     }
 
     #[task(
-        binds = TIMER_IRQ_0,
         priority = 1,
-        shared = [timer, alarm, led],
+        shared = [led],
         local = [tog: bool = true],
     )]
-    fn timer_irq(mut c: timer_irq::Context) {
+    fn blink(mut c: blink::Context) {
         if *c.local.tog {
             c.shared.led.lock(|l| l.set_high().unwrap());
         } else {
@@ -78,10 +86,6 @@ mod app {
         }
         *c.local.tog = !*c.local.tog;
 
-        let mut alarm = c.shared.alarm;
-        (alarm).lock(|a| {
-            a.clear_interrupt();
-            let _ = a.schedule(SCAN_TIME_US.microseconds());
-        });
+        blink::spawn_after(SCAN_TIME).unwrap();
     }
 }
@@ -69,6 +69,7 @@ fn main() -> ! {
         pac.USBCTRL_DPRAM,
         clocks.usb_clock,
         true,
+        false,
         &mut pac.RESETS,
     ));
 
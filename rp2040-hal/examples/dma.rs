@@ -0,0 +1,89 @@
+//! # DMA Example
+//!
+//! This application demonstrates how to copy memory buffers using a DMA
+//! channel.
+//!
+//! It turns on the LED if the copied buffer matches the source buffer.
+//!
+//! It may need to be adapted to your particular board layout and/or pin
+//! assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+// Some traits we need
+use embedded_hal::digital::v2::OutputPin;
+use hal::dma::{single_buffer, DMAExt};
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, copies a buffer with the
+/// DMA unit and then lights the LED if the copy succeeded.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins to their default state
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+    let mut led_pin = pins.gpio25.into_push_pull_output();
+
+    // Split the DMA unit into its channels
+    let dma = pac.DMA.split(&mut pac.RESETS);
+
+    // Buffers passed to the DMA unit need to live for the whole program, as
+    // the transfer could outlive the stack frame of the caller
+    let src = cortex_m::singleton!(: [u32; 16] = [0; 16]).unwrap();
+    let dst = cortex_m::singleton!(: [u32; 16] = [0; 16]).unwrap();
+    for (i, word) in src.iter_mut().enumerate() {
+        *word = i as u32;
+    }
+
+    // Copy the buffer and wait for the transfer to complete
+    let transfer = single_buffer::Config::new(dma.ch0, src, dst).start();
+    let (_ch0, src, dst) = transfer.wait();
+
+    if src == dst {
+        led_pin.set_high().unwrap();
+    }
+
+    #[allow(clippy::empty_loop)]
+    loop {
+        // Empty loop
+    }
+}
+
+// End of file
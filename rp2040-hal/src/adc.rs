@@ -34,13 +34,56 @@
 //! let temperature_adc_counts: u16 = adc.read(&mut temperature_sensor).unwrap();
 //! ```
 //!
+//! Get the temperature in degrees Celsius and a pin voltage in millivolts, each averaged over 16
+//! conversions
+//! ```no_run
+//! use rp2040_hal::{adc::Adc, gpio::Pins, pac, Sio};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let sio = Sio::new(peripherals.SIO);
+//! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+//! // Enable adc
+//! let mut adc = Adc::new(peripherals.ADC, &mut peripherals.RESETS);
+//! // The reference voltage defaults to 3.3V, adjust it if your board differs
+//! adc.set_reference_voltage(3300.0);
+//! let mut temperature_sensor = adc.enable_temp_sensor();
+//! let celsius: f32 = adc.read_temperature(&mut temperature_sensor, 16);
+//! let mut adc_pin_0 = pins.gpio26.into_floating_input();
+//! let millivolts: f32 = adc.read_millivolts(&mut adc_pin_0, 16);
+//! ```
+//!
+//! Capture ADC readings continuously from several channels into the FIFO
+//! ```no_run
+//! use rp2040_hal::{adc::Adc, gpio::Pins, pac, Sio};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let sio = Sio::new(peripherals.SIO);
+//! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+//! // Enable adc
+//! let mut adc = Adc::new(peripherals.ADC, &mut peripherals.RESETS);
+//! let mut adc_pin_0 = pins.gpio26.into_floating_input();
+//! let mut adc_pin_1 = pins.gpio27.into_floating_input();
+//! // Sample both channels in turn, with a total sample rate of 48MHz / 480 = 100kSPS
+//! let mut fifo = adc
+//!     .build_fifo()
+//!     .clock_divider(479, 0)
+//!     .round_robin((&mut adc_pin_0, &mut adc_pin_1))
+//!     .start();
+//! loop {
+//!     if fifo.len() >= 2 {
+//!         let pin_0_counts: u16 = fifo.read();
+//!         let pin_1_counts: u16 = fifo.read();
+//!     }
+//! }
+//! ```
+//!
 //! See [examples/adc.rs](https://github.com/rp-rs/rp-hal/tree/main/rp2040-hal/examples/adc.rs) and
 //! [pimoroni_pico_explorer_showcase.rs](https://github.com/rp-rs/rp-hal/tree/main/boards/pimoroni_pico_explorer/examples/pimoroni_pico_explorer_showcase.rs) for more complete examples
 
+use core::marker::PhantomData;
 use hal::adc::{Channel, OneShot};
 use pac::{ADC, RESETS};
 
 use crate::{
+    dma::{EndlessReadTarget, ReadTarget},
     gpio::Pin,
     gpio::{
         bank0::{Gpio26, Gpio27, Gpio28, Gpio29},
@@ -51,9 +94,63 @@ use crate::{
 
 const TEMPERATURE_SENSOR_CHANNEL: u8 = 4;
 
+/// Full scale reading of the 12 bit ADC
+const FULL_SCALE_COUNTS: f32 = 4096.0;
+
+/// Reference voltage of the Raspberry Pi Pico and most other boards
+const DEFAULT_REFERENCE_MILLIVOLTS: f32 = 3300.0;
+
+/// Two-point calibration of the ADC readings.
+///
+/// Maps ADC counts to millivolts using two readings of known voltages, which corrects the offset
+/// and gain errors of the ADC and any deviation of the reference voltage.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Calibration {
+    /// Millivolts per count
+    gain: f32,
+    /// Millivolts at zero counts
+    offset: f32,
+}
+
+impl Calibration {
+    /// Create a calibration from the counts read for two known voltages.
+    ///
+    /// The two voltages should be far apart, ideally close to each end of the ADC range. Returns
+    /// `None` if both readings have the same counts, as no gain can be derived from them.
+    pub fn from_two_points(
+        counts_low: u16,
+        millivolts_low: f32,
+        counts_high: u16,
+        millivolts_high: f32,
+    ) -> Option<Self> {
+        if counts_high == counts_low {
+            return None;
+        }
+        let gain = (millivolts_high - millivolts_low) / (counts_high as f32 - counts_low as f32);
+        Some(Self {
+            gain,
+            offset: millivolts_low - gain * counts_low as f32,
+        })
+    }
+
+    fn to_millivolts(self, counts: f32) -> f32 {
+        self.offset + self.gain * counts
+    }
+}
+
+/// Convert the voltage of the temperature sensor to degrees Celsius.
+///
+/// Uses the typical characteristics of the sensor given in the datasheet: 0.706V at 27 degrees
+/// Celsius, with a slope of -1.721mV per degree.
+pub fn temperature_from_millivolts(millivolts: f32) -> f32 {
+    27.0 - (millivolts - 706.0) / 1.721
+}
+
 /// Adc
 pub struct Adc {
     device: ADC,
+    reference_millivolts: f32,
+    calibration: Option<Calibration>,
 }
 
 impl Adc {
@@ -68,7 +165,11 @@ impl Adc {
         // Wait for adc ready
         while !device.cs.read().ready().bit_is_set() {}
 
-        Self { device }
+        Self {
+            device,
+            reference_millivolts: DEFAULT_REFERENCE_MILLIVOLTS,
+            calibration: None,
+        }
     }
 
     /// Free underlying register block
@@ -92,6 +193,99 @@ impl Adc {
     pub fn disable_temp_sensor(&mut self, _: TempSense) {
         self.device.cs.modify(|_, w| w.ts_en().clear_bit());
     }
+
+    /// Set the voltage at the ADC_VREF pin, which is 3.3V by default.
+    pub fn set_reference_voltage(&mut self, millivolts: f32) {
+        self.reference_millivolts = millivolts;
+    }
+
+    /// Set a calibration to use instead of the reference voltage when converting to millivolts.
+    pub fn set_calibration(&mut self, calibration: Option<Calibration>) {
+        self.calibration = calibration;
+    }
+
+    /// Convert ADC counts to millivolts, using the calibration if one is set and the reference
+    /// voltage otherwise.
+    pub fn counts_to_millivolts(&self, counts: u16) -> f32 {
+        self.to_millivolts(counts as f32)
+    }
+
+    fn to_millivolts(&self, counts: f32) -> f32 {
+        match self.calibration {
+            Some(calibration) => calibration.to_millivolts(counts),
+            None => counts * self.reference_millivolts / FULL_SCALE_COUNTS,
+        }
+    }
+
+    /// Read a channel `samples` times and return the average of the counts.
+    ///
+    /// Averaging reduces the noise of the readings. `samples` is treated as 1 if it is 0.
+    pub fn read_averaged<PIN: Channel<Adc, ID = u8>>(
+        &mut self,
+        _pin: &mut PIN,
+        samples: u16,
+    ) -> f32 {
+        let samples = samples.max(1);
+        let sum: u32 = (0..samples)
+            .map(|_| self.convert(PIN::channel()) as u32)
+            .sum();
+        sum as f32 / samples as f32
+    }
+
+    /// Read the voltage of a channel in millivolts, averaged over `samples` conversions.
+    pub fn read_millivolts<PIN: Channel<Adc, ID = u8>>(
+        &mut self,
+        pin: &mut PIN,
+        samples: u16,
+    ) -> f32 {
+        let counts = self.read_averaged(pin, samples);
+        self.to_millivolts(counts)
+    }
+
+    /// Read the temperature of the chip in degrees Celsius, averaged over `samples` conversions.
+    ///
+    /// The result depends on an accurate reference voltage or calibration, see
+    /// [`temperature_from_millivolts`].
+    pub fn read_temperature(&mut self, sensor: &mut TempSense, samples: u16) -> f32 {
+        temperature_from_millivolts(self.read_millivolts(sensor, samples))
+    }
+
+    fn convert(&mut self, chan: u8) -> u16 {
+        if chan == TEMPERATURE_SENSOR_CHANNEL {
+            self.device.cs.modify(|_, w| w.ts_en().set_bit())
+        }
+
+        while !self.device.cs.read().ready().bit_is_set() {
+            cortex_m::asm::nop();
+        }
+
+        self.device
+            .cs
+            .modify(|_, w| unsafe { w.ainsel().bits(chan).start_once().set_bit() });
+
+        while !self.device.cs.read().ready().bit_is_set() {
+            cortex_m::asm::nop();
+        }
+
+        self.device.result.read().result().bits()
+    }
+
+    /// Configure the ADC for free-running capture into its FIFO.
+    ///
+    /// The returned builder samples channel 0 as fast as possible by default. Call
+    /// [`AdcFifoBuilder::start`] to start capturing.
+    pub fn build_fifo(&mut self) -> AdcFifoBuilder<'_, u16> {
+        AdcFifoBuilder {
+            adc: self,
+            div: (0, 0),
+            ainsel: 0,
+            rrobin: 0,
+            threshold: None,
+            err: false,
+            dreq: false,
+            marker: PhantomData,
+        }
+    }
 }
 
 macro_rules! channel {
@@ -132,24 +326,320 @@ where
     type Error = ();
 
     fn read(&mut self, _pin: &mut PIN) -> nb::Result<WORD, Self::Error> {
-        let chan = PIN::channel();
+        Ok(self.convert(PIN::channel()).into())
+    }
+}
 
-        if chan == 4 {
-            self.device.cs.modify(|_, w| w.ts_en().set_bit())
+/// Set of channels to sample in round-robin mode.
+///
+/// Can be created from a reference to a single channel, or from a tuple of up to five references
+/// to channels, see [`AdcFifoBuilder::round_robin`].
+pub struct RoundRobin(u8);
+
+impl<PIN: Channel<Adc, ID = u8>> From<&PIN> for RoundRobin {
+    fn from(_: &PIN) -> Self {
+        Self(1 << PIN::channel())
+    }
+}
+
+impl<PIN: Channel<Adc, ID = u8>> From<&mut PIN> for RoundRobin {
+    fn from(_: &mut PIN) -> Self {
+        Self(1 << PIN::channel())
+    }
+}
+
+macro_rules! round_robin {
+    ($($PIN:ident),+) => {
+        impl<$($PIN),+> From<($(&mut $PIN),+)> for RoundRobin
+        where
+            $($PIN: Channel<Adc, ID = u8>),+
+        {
+            fn from(_: ($(&mut $PIN),+)) -> Self {
+                Self(0 $(| (1 << $PIN::channel()))+)
+            }
         }
+    };
+}
 
-        while !self.device.cs.read().ready().bit_is_set() {
-            cortex_m::asm::nop();
+round_robin!(A, B);
+round_robin!(A, B, C);
+round_robin!(A, B, C, D);
+round_robin!(A, B, C, D, E);
+
+/// Builder for free-running ADC capture into the FIFO. Obtained by calling [`Adc::build_fifo`].
+///
+/// `Word` is the type of the samples read from the FIFO: `u16` for the full 12 bit resolution,
+/// or `u8` after calling [`shift_8bit`](AdcFifoBuilder::shift_8bit).
+pub struct AdcFifoBuilder<'a, Word> {
+    adc: &'a mut Adc,
+    div: (u16, u8),
+    ainsel: u8,
+    rrobin: u8,
+    threshold: Option<u8>,
+    err: bool,
+    dreq: bool,
+    marker: PhantomData<Word>,
+}
+
+impl<'a, Word> AdcFifoBuilder<'a, Word> {
+    /// Set the clock divider which determines the sample rate.
+    ///
+    /// A new conversion is started every `1 + int + frac / 256` cycles of the 48MHz ADC clock. A
+    /// conversion takes 96 cycles, so smaller values result in back-to-back conversions at
+    /// 500kSPS. With several channels in round-robin mode, the sample rate is shared between the
+    /// channels.
+    pub fn clock_divider(mut self, int: u16, frac: u8) -> Self {
+        self.div = (int, frac);
+        self
+    }
+
+    /// Select the channel to sample, or the first channel to sample in round-robin mode.
+    pub fn set_channel<PIN: Channel<Adc, ID = u8>>(mut self, _pin: &mut PIN) -> Self {
+        self.ainsel = PIN::channel();
+        self
+    }
+
+    /// Sample the given channels in turn.
+    ///
+    /// Sampling starts with the channel selected by [`set_channel`](AdcFifoBuilder::set_channel)
+    /// and then continues with the next selected channel in ascending order. Without an explicit
+    /// selection, sampling starts with the lowest selected channel.
+    pub fn round_robin<T: Into<RoundRobin>>(mut self, selected_channels: T) -> Self {
+        let RoundRobin(rrobin) = selected_channels.into();
+        if self.ainsel == 0 && rrobin & 1 == 0 && rrobin != 0 {
+            self.ainsel = rrobin.trailing_zeros() as u8;
         }
+        self.rrobin = rrobin;
+        self
+    }
 
-        self.device
+    /// Raise `ADC_IRQ_FIFO` when the FIFO holds at least `threshold` samples.
+    ///
+    /// The FIFO has 4 entries. The interrupt is level triggered and clears as soon as enough
+    /// samples have been read.
+    pub fn enable_interrupt(mut self, threshold: u8) -> Self {
+        self.threshold = Some(threshold);
+        self
+    }
+
+    /// Set bit 15 of each sample if its conversion failed.
+    ///
+    /// The flag is available through [`AdcFifo::read_with_error`] and [`AdcFifo::read`] strips
+    /// it. It is not available with [`shift_8bit`](AdcFifoBuilder::shift_8bit).
+    pub fn enable_error_flag(mut self) -> Self {
+        self.err = true;
+        self
+    }
+
+    /// Assert the ADC's DREQ signal while the FIFO holds samples, so that they can be read by
+    /// the DMA unit using [`AdcFifo::dma_read_target`].
+    pub fn enable_dma(mut self) -> Self {
+        self.dreq = true;
+        self
+    }
+
+    /// Shift the samples right by 4 bits, so that each of them fits into a byte.
+    ///
+    /// This is useful for DMA transfers into byte buffers.
+    pub fn shift_8bit(self) -> AdcFifoBuilder<'a, u8> {
+        AdcFifoBuilder {
+            adc: self.adc,
+            div: self.div,
+            ainsel: self.ainsel,
+            rrobin: self.rrobin,
+            threshold: self.threshold,
+            err: false,
+            dreq: self.dreq,
+            marker: PhantomData,
+        }
+    }
+
+    /// Start capturing samples into the FIFO.
+    pub fn start(self) -> AdcFifo<'a, Word> {
+        let device = &self.adc.device;
+        let shift = core::mem::size_of::<Word>() == 1;
+
+        while device.cs.read().ready().bit_is_clear() {}
+
+        device
+            .div
+            .write(|w| unsafe { w.int().bits(self.div.0).frac().bits(self.div.1) });
+        device.fcs.write(|w| unsafe {
+            w.en()
+                .set_bit()
+                .shift()
+                .bit(shift)
+                .err()
+                .bit(self.err)
+                .dreq_en()
+                .bit(self.dreq)
+                .thresh()
+                .bits(self.threshold.unwrap_or(1))
+                // Clear the sticky flags
+                .over()
+                .set_bit()
+                .under()
+                .set_bit()
+        });
+        device
+            .inte
+            .write(|w| w.fifo().bit(self.threshold.is_some()));
+        device.cs.modify(|_, w| unsafe {
+            w.ainsel()
+                .bits(self.ainsel)
+                .rrobin()
+                .bits(self.rrobin)
+                .start_many()
+                .set_bit()
+        });
+
+        AdcFifo {
+            adc: self.adc,
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Free-running ADC capture into the FIFO. Obtained by calling [`AdcFifoBuilder::start`].
+///
+/// Conversions keep running until [`stop`](AdcFifo::stop) is called. If the FIFO is not read
+/// quickly enough, newer samples are lost and the overflow flag is set.
+pub struct AdcFifo<'a, Word> {
+    adc: &'a mut Adc,
+    marker: PhantomData<Word>,
+}
+
+impl<'a, Word> AdcFifo<'a, Word> {
+    /// Returns the number of samples waiting in the FIFO.
+    pub fn len(&self) -> u8 {
+        self.adc.device.fcs.read().level().bits()
+    }
+
+    /// Returns whether the FIFO is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns whether the FIFO has overflown since the last call, and clears the flag.
+    pub fn is_over(&mut self) -> bool {
+        let over = self.adc.device.fcs.read().over().bit_is_set();
+        if over {
+            self.adc.device.fcs.modify(|_, w| w.over().set_bit());
+        }
+        over
+    }
+
+    /// Returns whether the FIFO was read while empty since the last call, and clears the flag.
+    pub fn is_under(&mut self) -> bool {
+        let under = self.adc.device.fcs.read().under().bit_is_set();
+        if under {
+            self.adc.device.fcs.modify(|_, w| w.under().set_bit());
+        }
+        under
+    }
+
+    /// Returns a target which can be passed to DMA transfers to read samples from the FIFO.
+    ///
+    /// The capture has to be configured with [`AdcFifoBuilder::enable_dma`] for the transfer to
+    /// be paced by the ADC.
+    pub fn dma_read_target(&self) -> DmaReadTarget<Word> {
+        DmaReadTarget(self.adc.device.fifo.as_ptr() as u32, PhantomData)
+    }
+
+    /// Stop capturing and disable the FIFO, returning the ADC.
+    ///
+    /// Any samples remaining in the FIFO are discarded.
+    pub fn stop(self) -> &'a mut Adc {
+        let device = &self.adc.device;
+        device
             .cs
-            .modify(|_, w| unsafe { w.ainsel().bits(chan).start_once().set_bit() });
+            .modify(|_, w| unsafe { w.start_many().clear_bit().rrobin().bits(0).ainsel().bits(0) });
+        device.inte.write(|w| w.fifo().clear_bit());
+        // Wait for the running conversion to finish, then drain the FIFO.
+        while device.cs.read().ready().bit_is_clear() {}
+        while device.fcs.read().empty().bit_is_clear() {
+            let _ = device.fifo.read();
+        }
+        device.fcs.write(|w| w.over().set_bit().under().set_bit());
+        self.adc
+    }
+}
 
-        while !self.device.cs.read().ready().bit_is_set() {
-            cortex_m::asm::nop();
+impl<'a> AdcFifo<'a, u16> {
+    /// Read a sample from the FIFO.
+    ///
+    /// Returns an undefined value and sets the underflow flag if the FIFO is empty.
+    pub fn read(&mut self) -> u16 {
+        self.adc.device.fifo.read().val().bits()
+    }
+
+    /// Read a sample from the FIFO, returning `Err` with the sample if the conversion failed.
+    ///
+    /// Conversion errors are only reported if [`AdcFifoBuilder::enable_error_flag`] was used.
+    pub fn read_with_error(&mut self) -> Result<u16, u16> {
+        let sample = self.adc.device.fifo.read();
+        if sample.err().bit_is_set() {
+            Err(sample.val().bits())
+        } else {
+            Ok(sample.val().bits())
         }
+    }
+}
+
+impl<'a> AdcFifo<'a, u8> {
+    /// Read a sample from the FIFO.
+    ///
+    /// Returns an undefined value and sets the underflow flag if the FIFO is empty.
+    pub fn read(&mut self) -> u8 {
+        self.adc.device.fifo.read().val().bits() as u8
+    }
+}
+
+/// DMA source for the ADC FIFO. Obtained by calling [`AdcFifo::dma_read_target`].
+pub struct DmaReadTarget<Word>(u32, PhantomData<Word>);
+
+// Safety: The FIFO register stays valid and its address never changes.
+unsafe impl<Word: crate::dma::Word> ReadTarget for DmaReadTarget<Word> {
+    type ReceivedWord = Word;
+
+    fn rx_treq(&self) -> Option<u8> {
+        Some(crate::dma::DREQ_ADC)
+    }
+
+    fn rx_address_count(&self) -> (u32, u32) {
+        (self.0, u32::MAX)
+    }
+
+    fn rx_increment(&self) -> bool {
+        false
+    }
+}
+
+impl<Word: crate::dma::Word> EndlessReadTarget for DmaReadTarget<Word> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temperature_from_millivolts() {
+        assert_eq!(temperature_from_millivolts(706.0), 27.0);
+        assert!((temperature_from_millivolts(706.0 - 17.21) - 37.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_two_point_calibration() {
+        let calibration = Calibration::from_two_points(100, 100.0, 4000, 3200.0).unwrap();
+        assert!((calibration.to_millivolts(100.0) - 100.0).abs() < 0.001);
+        assert!((calibration.to_millivolts(4000.0) - 3200.0).abs() < 0.001);
+        assert!((calibration.to_millivolts(2050.0) - 1650.0).abs() < 0.001);
+    }
 
-        Ok(self.device.result.read().result().bits().into())
+    #[test]
+    fn test_two_point_calibration_equal_counts() {
+        assert_eq!(
+            Calibration::from_two_points(2000, 1000.0, 2000, 2000.0),
+            None
+        );
     }
 }
@@ -0,0 +1,71 @@
+//! Helpers shared by the drivers implementing the `async` feature
+
+use core::cell::UnsafeCell;
+use core::task::{Poll, Waker};
+
+/// Storage for a single [`Waker`], shared between a future and an interrupt handler.
+///
+/// The RP2040 has no atomic compare-and-swap instructions, so access is guarded by a critical
+/// section instead.
+pub(crate) struct WakerCell {
+    waker: UnsafeCell<Option<Waker>>,
+}
+
+// Safety: The waker is only accessed from within a critical section.
+unsafe impl Sync for WakerCell {}
+
+impl WakerCell {
+    /// Create an empty cell.
+    pub(crate) const fn new() -> Self {
+        Self {
+            waker: UnsafeCell::new(None),
+        }
+    }
+
+    /// Store `waker`, replacing any previously registered waker.
+    pub(crate) fn register(&self, waker: &Waker) {
+        critical_section::with(|_| {
+            // Safety: We are in a critical section.
+            let slot = unsafe { &mut *self.waker.get() };
+            match slot {
+                Some(old) if old.will_wake(waker) => {}
+                _ => *slot = Some(waker.clone()),
+            }
+        });
+    }
+
+    /// Wake and remove the registered waker, if any.
+    pub(crate) fn wake(&self) {
+        // Safety: We are in a critical section.
+        let waker = critical_section::with(|_| unsafe { (*self.waker.get()).take() });
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// Wait until `ready` returns `true`.
+///
+/// `enable` is called after registering the waker, and should enable the interrupt signalling
+/// that `ready` might have become `true`. The interrupt handler is expected to disable the
+/// interrupt again and to wake `waker`.
+pub(crate) async fn wait_until(
+    waker: &WakerCell,
+    mut ready: impl FnMut() -> bool,
+    mut enable: impl FnMut(),
+) {
+    futures::future::poll_fn(|cx| {
+        if ready() {
+            return Poll::Ready(());
+        }
+        waker.register(cx.waker());
+        enable();
+        // The condition could have changed before the interrupt was enabled.
+        if ready() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await
+}
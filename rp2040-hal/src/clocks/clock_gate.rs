@@ -0,0 +1,246 @@
+//! Clock gating with `WAKE_EN0/1` and `SLEEP_EN0/1`
+//!
+//! Every peripheral is connected to its clocks through a gate. The gates listed in `WAKE_EN0/1`
+//! are open while the processors are running, the ones listed in `SLEEP_EN0/1` while both
+//! processors are in deep sleep (`wfi`/`wfe` with `SCR.SLEEPDEEP` set). All gates are open after
+//! reset.
+use pac::clocks::RegisterBlock;
+
+/// A clock branch which can be gated, named after the clock and the peripheral it feeds
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ClockGate {
+    /// `clk_sys_clocks`
+    SysClocks = 0,
+    /// `clk_adc_adc`
+    AdcAdc = 1,
+    /// `clk_sys_adc`
+    SysAdc = 2,
+    /// `clk_sys_busctrl`
+    SysBusctrl = 3,
+    /// `clk_sys_busfabric`
+    SysBusfabric = 4,
+    /// `clk_sys_dma`
+    SysDma = 5,
+    /// `clk_sys_i2c0`
+    SysI2c0 = 6,
+    /// `clk_sys_i2c1`
+    SysI2c1 = 7,
+    /// `clk_sys_io`
+    SysIo = 8,
+    /// `clk_sys_jtag`
+    SysJtag = 9,
+    /// `clk_sys_vreg_and_chip_reset`
+    SysVregAndChipReset = 10,
+    /// `clk_sys_pads`
+    SysPads = 11,
+    /// `clk_sys_pio0`
+    SysPio0 = 12,
+    /// `clk_sys_pio1`
+    SysPio1 = 13,
+    /// `clk_sys_pll_sys`
+    SysPllSys = 14,
+    /// `clk_sys_pll_usb`
+    SysPllUsb = 15,
+    /// `clk_sys_psm`
+    SysPsm = 16,
+    /// `clk_sys_pwm`
+    SysPwm = 17,
+    /// `clk_sys_resets`
+    SysResets = 18,
+    /// `clk_sys_rom`
+    SysRom = 19,
+    /// `clk_sys_rosc`
+    SysRosc = 20,
+    /// `clk_rtc_rtc`
+    RtcRtc = 21,
+    /// `clk_sys_rtc`
+    SysRtc = 22,
+    /// `clk_sys_sio`
+    SysSio = 23,
+    /// `clk_peri_spi0`
+    PeriSpi0 = 24,
+    /// `clk_sys_spi0`
+    SysSpi0 = 25,
+    /// `clk_peri_spi1`
+    PeriSpi1 = 26,
+    /// `clk_sys_spi1`
+    SysSpi1 = 27,
+    /// `clk_sys_sram0`
+    SysSram0 = 28,
+    /// `clk_sys_sram1`
+    SysSram1 = 29,
+    /// `clk_sys_sram2`
+    SysSram2 = 30,
+    /// `clk_sys_sram3`
+    SysSram3 = 31,
+    /// `clk_sys_sram4`
+    SysSram4 = 32,
+    /// `clk_sys_sram5`
+    SysSram5 = 33,
+    /// `clk_sys_syscfg`
+    SysSyscfg = 34,
+    /// `clk_sys_sysinfo`
+    SysSysinfo = 35,
+    /// `clk_sys_tbman`
+    SysTbman = 36,
+    /// `clk_sys_timer`
+    SysTimer = 37,
+    /// `clk_peri_uart0`
+    PeriUart0 = 38,
+    /// `clk_sys_uart0`
+    SysUart0 = 39,
+    /// `clk_peri_uart1`
+    PeriUart1 = 40,
+    /// `clk_sys_uart1`
+    SysUart1 = 41,
+    /// `clk_sys_usbctrl`
+    SysUsbctrl = 42,
+    /// `clk_usb_usbctrl`
+    UsbUsbctrl = 43,
+    /// `clk_sys_watchdog`
+    SysWatchdog = 44,
+    /// `clk_sys_xip`
+    SysXip = 45,
+    /// `clk_sys_xosc`
+    SysXosc = 46,
+}
+
+/// A set of open clock gates, i.e. the contents of `WAKE_EN0/1` or `SLEEP_EN0/1`
+///
+/// ```no_run
+/// use rp2040_hal::clocks::{ClockGate, ClockGates};
+///
+/// // Stop PWM and SPI0 while running
+/// let gates = ClockGates::all()
+///     .disable(ClockGate::SysPwm)
+///     .disable(ClockGate::PeriSpi0)
+///     .disable(ClockGate::SysSpi0);
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ClockGates {
+    en0: u32,
+    en1: u32,
+}
+
+impl ClockGates {
+    /// Mask of the valid bits in `WAKE_EN1` and `SLEEP_EN1`
+    const EN1_MASK: u32 = 0x7fff;
+
+    /// All gates open, the reset state
+    pub const fn all() -> Self {
+        Self {
+            en0: u32::MAX,
+            en1: Self::EN1_MASK,
+        }
+    }
+
+    /// All gates closed
+    pub const fn none() -> Self {
+        Self { en0: 0, en1: 0 }
+    }
+
+    /// Open the gate of `gate`.
+    pub const fn enable(self, gate: ClockGate) -> Self {
+        let bit = gate as u32;
+        if bit < 32 {
+            Self {
+                en0: self.en0 | (1 << bit),
+                ..self
+            }
+        } else {
+            Self {
+                en1: self.en1 | (1 << (bit - 32)),
+                ..self
+            }
+        }
+    }
+
+    /// Close the gate of `gate`.
+    pub const fn disable(self, gate: ClockGate) -> Self {
+        let bit = gate as u32;
+        if bit < 32 {
+            Self {
+                en0: self.en0 & !(1 << bit),
+                ..self
+            }
+        } else {
+            Self {
+                en1: self.en1 & !(1 << (bit - 32)),
+                ..self
+            }
+        }
+    }
+
+    /// Check if the gate of `gate` is open.
+    pub const fn is_enabled(&self, gate: ClockGate) -> bool {
+        let bit = gate as u32;
+        if bit < 32 {
+            self.en0 & (1 << bit) != 0
+        } else {
+            self.en1 & (1 << (bit - 32)) != 0
+        }
+    }
+
+    pub(crate) fn read_wake(clocks: &RegisterBlock) -> Self {
+        Self {
+            en0: clocks.wake_en0.read().bits(),
+            en1: clocks.wake_en1.read().bits(),
+        }
+    }
+
+    pub(crate) fn write_wake(self, clocks: &RegisterBlock) {
+        clocks.wake_en0.write(|w| unsafe { w.bits(self.en0) });
+        clocks.wake_en1.write(|w| unsafe { w.bits(self.en1) });
+    }
+
+    pub(crate) fn read_sleep(clocks: &RegisterBlock) -> Self {
+        Self {
+            en0: clocks.sleep_en0.read().bits(),
+            en1: clocks.sleep_en1.read().bits(),
+        }
+    }
+
+    pub(crate) fn write_sleep(self, clocks: &RegisterBlock) {
+        clocks.sleep_en0.write(|w| unsafe { w.bits(self.en0) });
+        clocks.sleep_en1.write(|w| unsafe { w.bits(self.en1) });
+    }
+}
+
+impl Default for ClockGates {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_gates() {
+        assert_eq!(
+            ClockGates::all().en1,
+            (1 << (ClockGate::SysXosc as u32 - 31)) - 1
+        );
+        assert_eq!(
+            ClockGates::none().enable(ClockGate::RtcRtc),
+            ClockGates {
+                en0: 1 << 21,
+                en1: 0
+            }
+        );
+        let gates = ClockGates::all()
+            .disable(ClockGate::SysPwm)
+            .disable(ClockGate::UsbUsbctrl);
+        assert_eq!(gates.en0, !(1 << 17));
+        assert_eq!(gates.en1, ClockGates::EN1_MASK & !(1 << 11));
+        assert!(!gates.is_enabled(ClockGate::SysPwm));
+        assert!(gates.is_enabled(ClockGate::SysSpi0));
+        assert!(!gates
+            .enable(ClockGate::SysPwm)
+            .disable(ClockGate::SysSpi0)
+            .is_enabled(ClockGate::SysSpi0));
+    }
+}
@@ -76,8 +76,11 @@ use pac::{CLOCKS, PLL_SYS, PLL_USB, RESETS, XOSC};
 
 #[macro_use]
 mod macros;
+mod clock_gate;
 mod clock_sources;
 
+pub use clock_gate::{ClockGate, ClockGates};
+
 use clock_sources::PllSys;
 
 use self::clock_sources::{GPin0, GPin1, PllUsb, Rosc, Xosc};
@@ -300,6 +303,32 @@ impl ClocksManager {
             .configure_clock(&self.system_clock, self.system_clock.freq())
     }
 
+    /// Clock gates which are open while the processors are running
+    pub fn wake_enabled(&self) -> ClockGates {
+        ClockGates::read_wake(&self.clocks)
+    }
+
+    /// Select the clock gates which are open while the processors are running.
+    ///
+    /// Peripherals whose clocks are gated stop working. Make sure the gates required to run the
+    /// program, e.g. the SRAM banks, `clk_sys_rom`, `clk_sys_xip` and the bus fabric, stay open.
+    pub fn set_wake_enabled(&mut self, gates: ClockGates) {
+        gates.write_wake(&self.clocks);
+    }
+
+    /// Clock gates which are open while both processors are in deep sleep
+    pub fn sleep_enabled(&self) -> ClockGates {
+        ClockGates::read_sleep(&self.clocks)
+    }
+
+    /// Select the clock gates which are open while both processors are in deep sleep, i.e.
+    /// waiting in `wfi` or `wfe` with `SCR.SLEEPDEEP` set.
+    ///
+    /// The peripheral which is expected to wake up the processors needs to keep its clocks.
+    pub fn set_sleep_enabled(&mut self, gates: ClockGates) {
+        gates.write_sleep(&self.clocks);
+    }
+
     /// Releases the CLOCKS block
     pub fn free(self) -> CLOCKS {
         self.clocks
@@ -358,7 +387,7 @@ pub fn init_clocks_and_plls(
 
 // Calculates (numerator<<8)/denominator, avoiding 64bit division
 // Returns None if the result would not fit in 32 bit.
-fn fractional_div(numerator: u32, denominator: u32) -> Option<u32> {
+pub(crate) fn fractional_div(numerator: u32, denominator: u32) -> Option<u32> {
     if denominator.eq(&numerator) {
         return Some(1 << 8);
     }
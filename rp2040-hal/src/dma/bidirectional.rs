@@ -0,0 +1,105 @@
+//! Bidirectional DMA transfers
+//!
+//! A bidirectional transfer uses two DMA channels to simultaneously write data to and read data
+//! from a peripheral, as required for full-duplex buses like SPI. The first channel copies the
+//! source buffer into the peripheral while the second channel copies the data received by the
+//! peripheral into the destination buffer.
+
+use super::{single_channel::ChannelConfig, Pace, ReadTarget, SingleChannel, WriteTarget};
+use core::sync::atomic::{compiler_fence, Ordering};
+
+/// DMA configuration for sending and receiving data simultaneously
+pub struct Config<CH1, CH2, FROM, BIDI, TO>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget,
+    BIDI: ReadTarget + WriteTarget,
+    TO: WriteTarget,
+{
+    ch: (CH1, CH2),
+    from: FROM,
+    bidi: BIDI,
+    to: TO,
+}
+
+impl<CH1, CH2, FROM, BIDI, TO, WORD> Config<CH1, CH2, FROM, BIDI, TO>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    BIDI: ReadTarget<ReceivedWord = WORD> + WriteTarget<TransmittedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Create a DMA configuration for sending `from` to `bidi` while storing the data received by
+    /// `bidi` in `to`.
+    ///
+    /// The first channel is used for sending, the second one for receiving.
+    pub fn new(ch: (CH1, CH2), from: FROM, bidi: BIDI, to: TO) -> Self {
+        Config { ch, from, bidi, to }
+    }
+
+    /// Start the DMA transfer
+    pub fn start(mut self) -> Transfer<CH1, CH2, FROM, BIDI, TO> {
+        // Make sure that memory contents reflect what the user intended.
+        cortex_m::asm::dsb();
+        compiler_fence(Ordering::SeqCst);
+
+        // Configure both channels, each paced by the peripheral, and start them together so
+        // that no received word is lost.
+        self.ch
+            .0
+            .config(&self.from, &mut self.bidi, Pace::PreferSink, None, false);
+        self.ch
+            .1
+            .config(&self.bidi, &mut self.to, Pace::PreferSource, None, false);
+        self.ch.0.start_both(&mut self.ch.1);
+
+        Transfer {
+            ch: self.ch,
+            from: self.from,
+            bidi: self.bidi,
+            to: self.to,
+        }
+    }
+}
+
+/// Instance of a bidirectional DMA transfer
+pub struct Transfer<CH1, CH2, FROM, BIDI, TO>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget,
+    BIDI: ReadTarget + WriteTarget,
+    TO: WriteTarget,
+{
+    ch: (CH1, CH2),
+    from: FROM,
+    bidi: BIDI,
+    to: TO,
+}
+
+impl<CH1, CH2, FROM, BIDI, TO, WORD> Transfer<CH1, CH2, FROM, BIDI, TO>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    BIDI: ReadTarget<ReceivedWord = WORD> + WriteTarget<TransmittedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Returns whether both the sending and the receiving side have completed.
+    pub fn is_done(&self) -> bool {
+        !(self.ch.0.is_busy() || self.ch.1.is_busy())
+    }
+
+    /// Block until the transfer is complete, returning the channels and the buffers.
+    pub fn wait(self) -> ((CH1, CH2), FROM, BIDI, TO) {
+        while !self.is_done() {}
+
+        // Make sure that memory contents reflect what the user intended.
+        cortex_m::asm::dsb();
+        compiler_fence(Ordering::SeqCst);
+
+        (self.ch, self.from, self.bidi, self.to)
+    }
+}
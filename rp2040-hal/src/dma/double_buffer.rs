@@ -0,0 +1,311 @@
+//! Double-buffered DMA Transfers
+//!
+//! A double-buffered transfer uses two DMA channels which are chained to each other via
+//! `CHAIN_TO`. While one channel copies into (or out of) one buffer, the next buffer is queued on
+//! the other channel, which the hardware starts as soon as the first channel has finished. This
+//! allows continuous streaming from or to a peripheral without gaps between the buffers.
+//!
+//! To receive data from a peripheral, the buffer which is being filled is the write target of the
+//! transfer and the next buffer is queued with [`Transfer::write_next`]:
+//!
+//! ```no_run
+//! use rp2040_hal::dma::{double_buffer, Channel, EndlessReadTarget, ReadTarget, CH0, CH1};
+//! # fn process(_buf: &[u32; 64]) {}
+//!
+//! // `rx` could for example be a PIO RX FIFO.
+//! fn capture<RX>(channels: (Channel<CH0>, Channel<CH1>), rx: RX) -> !
+//! where
+//!     RX: ReadTarget<ReceivedWord = u32> + EndlessReadTarget,
+//! {
+//!     let buf1 = cortex_m::singleton!(: [u32; 64] = [0; 64]).unwrap();
+//!     let buf2 = cortex_m::singleton!(: [u32; 64] = [0; 64]).unwrap();
+//!     let transfer = double_buffer::Config::new(channels, rx, buf1).start();
+//!     let mut transfer = transfer.write_next(buf2);
+//!     loop {
+//!         // Wait for the first buffer to be filled while the second one is queued.
+//!         let (full_buf, next_transfer) = transfer.wait();
+//!         process(full_buf);
+//!         // Hand the buffer back so it is filled once the current one is full.
+//!         transfer = next_transfer.write_next(full_buf);
+//!     }
+//! }
+//! ```
+//!
+//! Sending data works the same way, using [`Transfer::read_next`] to queue the next buffer to be
+//! read from.
+
+use super::{
+    single_channel::ChannelConfig, EndlessReadTarget, EndlessWriteTarget, Pace, ReadTarget,
+    SingleChannel, WriteTarget,
+};
+use core::sync::atomic::{compiler_fence, Ordering};
+
+/// Configuration for double-buffered DMA transfer
+pub struct Config<CH1: SingleChannel, CH2: SingleChannel, FROM: ReadTarget, TO: WriteTarget> {
+    ch: (CH1, CH2),
+    from: FROM,
+    to: TO,
+    pace: Pace,
+}
+
+impl<CH1, CH2, FROM, TO, WORD> Config<CH1, CH2, FROM, TO>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Create a DMA Config for the initial buffers of a double-buffered transfer.
+    ///
+    /// The first transfer runs on the first channel, the buffer passed to
+    /// [`Transfer::read_next`] or [`Transfer::write_next`] is then transferred by the second
+    /// channel, and so on.
+    pub fn new(ch: (CH1, CH2), from: FROM, to: TO) -> Config<CH1, CH2, FROM, TO> {
+        Config {
+            ch,
+            from,
+            to,
+            pace: Pace::PreferSource,
+        }
+    }
+
+    /// Sets the (preferred) pace for the DMA transfers.
+    ///
+    /// Usually, the code will automatically configure the correct pace, but
+    /// peripheral-to-peripheral transfers require the user to manually select whether the source
+    /// or the sink shall be queried for the pace signal.
+    pub fn pace(&mut self, pace: Pace) {
+        self.pace = pace;
+    }
+
+    /// Start the DMA transfer
+    pub fn start(mut self) -> Transfer<CH1, CH2, FROM, TO, ()> {
+        // Make sure that memory contents reflect what the user intended.
+        cortex_m::asm::dsb();
+        compiler_fence(Ordering::SeqCst);
+
+        // Configure the DMA channel and start it.
+        self.ch
+            .0
+            .config(&self.from, &mut self.to, self.pace, None, true);
+
+        Transfer {
+            ch: self.ch,
+            from: self.from,
+            to: self.to,
+            pace: self.pace,
+            state: (),
+            second_ch: false,
+        }
+    }
+}
+
+/// State for a double-buffered read
+pub struct ReadNext<BUF: ReadTarget>(BUF);
+/// State for a double-buffered write
+pub struct WriteNext<BUF: WriteTarget>(BUF);
+
+/// Instance of a double-buffered DMA transfer
+///
+/// `NEXT` is `()` while no further buffer is queued, [`ReadNext`] after a call to
+/// [`read_next`](Transfer::read_next), and [`WriteNext`] after a call to
+/// [`write_next`](Transfer::write_next).
+pub struct Transfer<CH1, CH2, FROM, TO, NEXT>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget,
+    TO: WriteTarget,
+{
+    ch: (CH1, CH2),
+    from: FROM,
+    to: TO,
+    pace: Pace,
+    state: NEXT,
+    second_ch: bool,
+}
+
+impl<CH1, CH2, FROM, TO, NEXT, WORD> Transfer<CH1, CH2, FROM, TO, NEXT>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Returns whether the transfer of the current buffer has completed.
+    pub fn is_done(&self) -> bool {
+        if self.second_ch {
+            !self.ch.1.is_busy()
+        } else {
+            !self.ch.0.is_busy()
+        }
+    }
+}
+
+impl<CH1, CH2, FROM, TO, WORD> Transfer<CH1, CH2, FROM, TO, ()>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Block until the transfer of the last queued buffer is complete, returning the channels and
+    /// the buffers.
+    pub fn wait(self) -> ((CH1, CH2), FROM, TO) {
+        while !self.is_done() {}
+
+        // Make sure that memory contents reflect what the user intended.
+        cortex_m::asm::dsb();
+        compiler_fence(Ordering::SeqCst);
+
+        (self.ch, self.from, self.to)
+    }
+}
+
+impl<CH1, CH2, FROM, TO, WORD> Transfer<CH1, CH2, FROM, TO, ()>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD> + EndlessWriteTarget,
+{
+    /// Queue a buffer to be read from once the current buffer has been transferred.
+    ///
+    /// The buffer is transferred by the other channel, which is chained to the current one so
+    /// that no data is lost between the two buffers.
+    pub fn read_next<BUF: ReadTarget<ReceivedWord = WORD>>(
+        mut self,
+        buf: BUF,
+    ) -> Transfer<CH1, CH2, FROM, TO, ReadNext<BUF>> {
+        // Make sure that memory contents reflect what the user intended.
+        cortex_m::asm::dsb();
+        compiler_fence(Ordering::SeqCst);
+
+        // Configure the _other_ DMA channel, but do not start it yet, and chain the currently
+        // running channel to it.
+        if self.second_ch {
+            self.ch.0.config(&buf, &mut self.to, self.pace, None, false);
+            self.ch.1.set_chain_to_enabled(&mut self.ch.0);
+        } else {
+            self.ch.1.config(&buf, &mut self.to, self.pace, None, false);
+            self.ch.0.set_chain_to_enabled(&mut self.ch.1);
+        }
+
+        Transfer {
+            ch: self.ch,
+            from: self.from,
+            to: self.to,
+            pace: self.pace,
+            state: ReadNext(buf),
+            second_ch: self.second_ch,
+        }
+    }
+}
+
+impl<CH1, CH2, FROM, TO, WORD> Transfer<CH1, CH2, FROM, TO, ()>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD> + EndlessReadTarget,
+    TO: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Queue a buffer to be written to once the current buffer has been filled.
+    ///
+    /// The buffer is transferred by the other channel, which is chained to the current one so
+    /// that no data is lost between the two buffers.
+    pub fn write_next<BUF: WriteTarget<TransmittedWord = WORD>>(
+        mut self,
+        mut buf: BUF,
+    ) -> Transfer<CH1, CH2, FROM, TO, WriteNext<BUF>> {
+        // Make sure that memory contents reflect what the user intended.
+        cortex_m::asm::dsb();
+        compiler_fence(Ordering::SeqCst);
+
+        // Configure the _other_ DMA channel, but do not start it yet, and chain the currently
+        // running channel to it.
+        if self.second_ch {
+            self.ch
+                .0
+                .config(&self.from, &mut buf, self.pace, None, false);
+            self.ch.1.set_chain_to_enabled(&mut self.ch.0);
+        } else {
+            self.ch
+                .1
+                .config(&self.from, &mut buf, self.pace, None, false);
+            self.ch.0.set_chain_to_enabled(&mut self.ch.1);
+        }
+
+        Transfer {
+            ch: self.ch,
+            from: self.from,
+            to: self.to,
+            pace: self.pace,
+            state: WriteNext(buf),
+            second_ch: self.second_ch,
+        }
+    }
+}
+
+impl<CH1, CH2, FROM, TO, NEXT, WORD> Transfer<CH1, CH2, FROM, TO, ReadNext<NEXT>>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD> + EndlessWriteTarget,
+    NEXT: ReadTarget<ReceivedWord = WORD>,
+{
+    /// Block until the current buffer has been read, returning it together with a transfer which
+    /// is now reading from the queued buffer.
+    pub fn wait(self) -> (FROM, Transfer<CH1, CH2, NEXT, TO, ()>) {
+        while !self.is_done() {}
+
+        // Make sure that memory contents reflect what the user intended.
+        cortex_m::asm::dsb();
+        compiler_fence(Ordering::SeqCst);
+
+        // Invert second_ch as now the other channel is the "active" channel.
+        (
+            self.from,
+            Transfer {
+                ch: self.ch,
+                from: self.state.0,
+                to: self.to,
+                pace: self.pace,
+                state: (),
+                second_ch: !self.second_ch,
+            },
+        )
+    }
+}
+
+impl<CH1, CH2, FROM, TO, NEXT, WORD> Transfer<CH1, CH2, FROM, TO, WriteNext<NEXT>>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD> + EndlessReadTarget,
+    TO: WriteTarget<TransmittedWord = WORD>,
+    NEXT: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Block until the current buffer has been filled, returning it together with a transfer
+    /// which is now writing to the queued buffer.
+    pub fn wait(self) -> (TO, Transfer<CH1, CH2, FROM, NEXT, ()>) {
+        while !self.is_done() {}
+
+        // Make sure that memory contents reflect what the user intended.
+        cortex_m::asm::dsb();
+        compiler_fence(Ordering::SeqCst);
+
+        // Invert second_ch as now the other channel is the "active" channel.
+        (
+            self.to,
+            Transfer {
+                ch: self.ch,
+                from: self.from,
+                to: self.state.0,
+                pace: self.pace,
+                state: (),
+                second_ch: !self.second_ch,
+            },
+        )
+    }
+}
@@ -0,0 +1,357 @@
+//! Direct memory access (DMA)
+//!
+//! See [Chapter 2 Section 5](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) for more details
+//!
+//! The DMA unit is split into its twelve channels, which are then passed by value into transfer
+//! configurations. A transfer takes ownership of its buffers for as long as the hardware may
+//! access them and returns everything once it has completed:
+//!
+//! ```no_run
+//! use rp2040_hal::{pac, dma::{DMAExt, single_buffer}};
+//!
+//! static mut SRC: [u32; 16] = [0x1234_5678; 16];
+//! static mut DST: [u32; 16] = [0; 16];
+//!
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let dma = peripherals.DMA.split(&mut peripherals.RESETS);
+//!
+//! // Safety: The buffers are only accessed through these references.
+//! let (src, dst) = unsafe { (&SRC, &mut DST) };
+//! let transfer = single_buffer::Config::new(dma.ch0, src, dst).start();
+//! // The CPU is free to do something else here.
+//! let (ch0, src, dst) = transfer.wait();
+//! ```
+//!
+//! Memory buffers are accepted through the [`ReadBuffer`] and [`WriteBuffer`] traits of the
+//! [`embedded-dma`](https://docs.rs/embedded-dma) crate, which guarantee that the memory stays
+//! valid while the transfer is running. Peripherals can take part in a transfer by implementing
+//! [`ReadTarget`] or [`WriteTarget`], in which case the transfer is paced by their `DREQ`
+//! signal.
+//!
+//! See [examples/dma.rs](https://github.com/rp-rs/rp-hal/tree/main/rp2040-hal/examples/dma.rs) for a more complete example
+
+use crate::resets::SubsystemReset;
+use core::marker::PhantomData;
+use embedded_dma::{ReadBuffer, WriteBuffer};
+use pac::{DMA, RESETS};
+
+pub mod bidirectional;
+pub mod double_buffer;
+pub mod ring;
+pub mod single_buffer;
+mod single_channel;
+
+pub use single_channel::SingleChannel;
+
+/// DMA unit.
+pub trait DMAExt {
+    /// Resets the DMA unit and splits it into its individual channels.
+    fn split(self, resets: &mut RESETS) -> Channels;
+}
+
+/// DMA channel.
+pub struct Channel<CH: ChannelIndex> {
+    _phantom: PhantomData<CH>,
+}
+
+/// DMA channel identifier.
+pub trait ChannelIndex {
+    /// Numerical index of the DMA channel (0..11).
+    fn id() -> u8;
+}
+
+macro_rules! channels {
+    (
+        $($CHX:ident: ($chX:ident, $x:expr),)+
+    ) => {
+        impl DMAExt for DMA {
+            fn split(self, resets: &mut RESETS) -> Channels {
+                self.reset_bring_down(resets);
+                self.reset_bring_up(resets);
+
+                Channels {
+                    $(
+                        $chX: Channel {
+                            _phantom: PhantomData,
+                        },
+                    )+
+                }
+            }
+        }
+
+        /// Set of DMA channels.
+        pub struct Channels {
+            $(
+                /// DMA channel.
+                pub $chX: Channel<$CHX>,
+            )+
+        }
+
+        $(
+            /// DMA channel identifier.
+            pub struct $CHX;
+
+            impl ChannelIndex for $CHX {
+                fn id() -> u8 {
+                    $x
+                }
+            }
+        )+
+    }
+}
+
+channels! {
+    CH0: (ch0, 0),
+    CH1: (ch1, 1),
+    CH2: (ch2, 2),
+    CH3: (ch3, 3),
+    CH4: (ch4, 4),
+    CH5: (ch5, 5),
+    CH6: (ch6, 6),
+    CH7: (ch7, 7),
+    CH8: (ch8, 8),
+    CH9: (ch9, 9),
+    CH10: (ch10, 10),
+    CH11: (ch11, 11),
+}
+
+/// Word size of a DMA transfer.
+///
+/// The DMA unit can move bytes, half-words and words.
+pub trait Word: private::Sealed {}
+
+impl Word for u8 {}
+impl Word for u16 {}
+impl Word for u32 {}
+impl Word for i8 {}
+impl Word for i16 {}
+impl Word for i32 {}
+
+mod private {
+    pub trait Sealed {}
+
+    impl Sealed for u8 {}
+    impl Sealed for u16 {}
+    impl Sealed for u32 {}
+    impl Sealed for i8 {}
+    impl Sealed for i16 {}
+    impl Sealed for i32 {}
+}
+
+/// Trait which is implemented by anything that can be read via DMA.
+///
+/// # Safety
+///
+/// The implementing type must be safe to use for DMA reads. This means:
+///
+/// - The range returned by `rx_address_count` must point to a valid address, and if
+///   `rx_increment` is true, `count` must fit into the allocated buffer.
+/// - As long as no `&mut self` method is called on the implementing object:
+///   - `rx_address_count` must always return the same value, if called multiple times.
+///   - The memory specified by the pointer and size returned by `rx_address_count` must not be
+///     freed during the transfer it is used in as long as `self` is not dropped.
+pub unsafe trait ReadTarget {
+    /// Type which is transferred in a single DMA transfer.
+    type ReceivedWord: Word;
+
+    /// Returns the DREQ number for this data source (`None` for memory buffers).
+    fn rx_treq(&self) -> Option<u8>;
+
+    /// Returns the address and the maximum number of words that can be transferred from this data
+    /// source in a single DMA operation.
+    ///
+    /// For peripherals, the count should likely be u32::MAX. If a data source implements
+    /// EndlessReadTarget, it is suitable for infinite transfers from or to ring buffers. Note that
+    /// ring buffers designated for endless transfers, but with a finite buffer size, should return
+    /// the size of their individual buffers here.
+    fn rx_address_count(&self) -> (u32, u32);
+
+    /// Returns whether the address shall be incremented after each transfer.
+    fn rx_increment(&self) -> bool;
+}
+
+/// Marker which signals that `rx_address_count()` can be called multiple times.
+///
+/// The DMA code will never call `rx_address_count()` to request more than two buffers to configure
+/// two DMA channels. In the case of peripherals, the function can always return the same values.
+pub trait EndlessReadTarget: ReadTarget {}
+
+// Safety: ReadBuffer::read_buffer() guarantees that the returned memory stays valid and unchanged
+// for as long as the buffer is not dropped.
+unsafe impl<B: ReadBuffer> ReadTarget for B
+where
+    B::Word: Word,
+{
+    type ReceivedWord = B::Word;
+
+    fn rx_treq(&self) -> Option<u8> {
+        None
+    }
+
+    fn rx_address_count(&self) -> (u32, u32) {
+        let (ptr, len) = unsafe { self.read_buffer() };
+        (ptr as u32, len as u32)
+    }
+
+    fn rx_increment(&self) -> bool {
+        true
+    }
+}
+
+/// Trait which is implemented by anything that can be written via DMA.
+///
+/// # Safety
+///
+/// The implementing type must be safe to use for DMA writes. This means:
+///
+/// - The range returned by `tx_address_count` must point to a valid address, and if
+///   `tx_increment` is true, `count` must fit into the allocated buffer.
+/// - As long as no other `&mut self` method is called on the implementing object:
+///   - `tx_address_count` must always return the same value, if called multiple times.
+///   - The memory specified by the pointer and size returned by `tx_address_count` must not be
+///     freed during the transfer it is used in as long as `self` is not dropped.
+pub unsafe trait WriteTarget {
+    /// Type which is transferred in a single DMA transfer.
+    type TransmittedWord: Word;
+
+    /// Returns the DREQ number for this data sink (`None` for memory buffers).
+    fn tx_treq(&self) -> Option<u8>;
+
+    /// Returns the address and the maximum number of words that can be transferred from this data
+    /// source in a single DMA operation.
+    ///
+    /// See `ReadTarget::rx_address_count` for a complete description of the semantics of this
+    /// function.
+    fn tx_address_count(&mut self) -> (u32, u32);
+
+    /// Returns whether the address shall be incremented after each transfer.
+    fn tx_increment(&self) -> bool;
+}
+
+/// Marker which signals that `tx_address_count()` can be called multiple times.
+///
+/// The DMA code will never call `tx_address_count()` to request more than two buffers to configure
+/// two DMA channels. In the case of peripherals, the function can always return the same values.
+pub trait EndlessWriteTarget: WriteTarget {}
+
+// Safety: WriteBuffer::write_buffer() guarantees that the returned memory stays valid for as long
+// as the buffer is not dropped.
+unsafe impl<B: WriteBuffer> WriteTarget for B
+where
+    B::Word: Word,
+{
+    type TransmittedWord = B::Word;
+
+    fn tx_treq(&self) -> Option<u8> {
+        None
+    }
+
+    fn tx_address_count(&mut self) -> (u32, u32) {
+        let (ptr, len) = unsafe { self.write_buffer() };
+        (ptr as u32, len as u32)
+    }
+
+    fn tx_increment(&self) -> bool {
+        true
+    }
+}
+
+/// Pacing for DMA transfers.
+///
+/// Generally, while memory-to-memory DMA transfers can operate at maximum possible throughput,
+/// transfers involving peripherals commonly have to wait for data to be available or for available
+/// space in write queues. This type defines whether the sink or the source shall pace the transfer
+/// for peripheral-to-peripheral transfers.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Pace {
+    /// The DREQ signal from the source is used, if available. If not, the sink's DREQ signal is
+    /// used.
+    PreferSource,
+    /// The DREQ signal from the sink is used, if available. If not, the source's DREQ signal is
+    /// used.
+    PreferSink,
+}
+
+/// The DREQ value which lets a channel run without pacing.
+pub(crate) const TREQ_UNPACED: u8 = 0x3f;
+
+/// The DREQ value for PIO0's TX FIFO 0
+pub const DREQ_PIO0_TX0: u8 = 0;
+/// The DREQ value for PIO0's TX FIFO 1
+pub const DREQ_PIO0_TX1: u8 = 1;
+/// The DREQ value for PIO0's TX FIFO 2
+pub const DREQ_PIO0_TX2: u8 = 2;
+/// The DREQ value for PIO0's TX FIFO 3
+pub const DREQ_PIO0_TX3: u8 = 3;
+/// The DREQ value for PIO0's RX FIFO 0
+pub const DREQ_PIO0_RX0: u8 = 4;
+/// The DREQ value for PIO0's RX FIFO 1
+pub const DREQ_PIO0_RX1: u8 = 5;
+/// The DREQ value for PIO0's RX FIFO 2
+pub const DREQ_PIO0_RX2: u8 = 6;
+/// The DREQ value for PIO0's RX FIFO 3
+pub const DREQ_PIO0_RX3: u8 = 7;
+/// The DREQ value for PIO1's TX FIFO 0
+pub const DREQ_PIO1_TX0: u8 = 8;
+/// The DREQ value for PIO1's TX FIFO 1
+pub const DREQ_PIO1_TX1: u8 = 9;
+/// The DREQ value for PIO1's TX FIFO 2
+pub const DREQ_PIO1_TX2: u8 = 10;
+/// The DREQ value for PIO1's TX FIFO 3
+pub const DREQ_PIO1_TX3: u8 = 11;
+/// The DREQ value for PIO1's RX FIFO 0
+pub const DREQ_PIO1_RX0: u8 = 12;
+/// The DREQ value for PIO1's RX FIFO 1
+pub const DREQ_PIO1_RX1: u8 = 13;
+/// The DREQ value for PIO1's RX FIFO 2
+pub const DREQ_PIO1_RX2: u8 = 14;
+/// The DREQ value for PIO1's RX FIFO 3
+pub const DREQ_PIO1_RX3: u8 = 15;
+/// The DREQ value for SPI0's TX FIFO
+pub const DREQ_SPI0_TX: u8 = 16;
+/// The DREQ value for SPI0's RX FIFO
+pub const DREQ_SPI0_RX: u8 = 17;
+/// The DREQ value for SPI1's TX FIFO
+pub const DREQ_SPI1_TX: u8 = 18;
+/// The DREQ value for SPI1's RX FIFO
+pub const DREQ_SPI1_RX: u8 = 19;
+/// The DREQ value for UART0's TX FIFO
+pub const DREQ_UART0_TX: u8 = 20;
+/// The DREQ value for UART0's RX FIFO
+pub const DREQ_UART0_RX: u8 = 21;
+/// The DREQ value for UART1's TX FIFO
+pub const DREQ_UART1_TX: u8 = 22;
+/// The DREQ value for UART1's RX FIFO
+pub const DREQ_UART1_RX: u8 = 23;
+/// The DREQ value for PWM Counter 0's Wrap Value
+pub const DREQ_PWM_WRAP0: u8 = 24;
+/// The DREQ value for PWM Counter 1's Wrap Value
+pub const DREQ_PWM_WRAP1: u8 = 25;
+/// The DREQ value for PWM Counter 2's Wrap Value
+pub const DREQ_PWM_WRAP2: u8 = 26;
+/// The DREQ value for PWM Counter 3's Wrap Value
+pub const DREQ_PWM_WRAP3: u8 = 27;
+/// The DREQ value for PWM Counter 4's Wrap Value
+pub const DREQ_PWM_WRAP4: u8 = 28;
+/// The DREQ value for PWM Counter 5's Wrap Value
+pub const DREQ_PWM_WRAP5: u8 = 29;
+/// The DREQ value for PWM Counter 6's Wrap Value
+pub const DREQ_PWM_WRAP6: u8 = 30;
+/// The DREQ value for PWM Counter 7's Wrap Value
+pub const DREQ_PWM_WRAP7: u8 = 31;
+/// The DREQ value for I2C0's TX FIFO
+pub const DREQ_I2C0_TX: u8 = 32;
+/// The DREQ value for I2C0's RX FIFO
+pub const DREQ_I2C0_RX: u8 = 33;
+/// The DREQ value for I2C1's TX FIFO
+pub const DREQ_I2C1_TX: u8 = 34;
+/// The DREQ value for I2C1's RX FIFO
+pub const DREQ_I2C1_RX: u8 = 35;
+/// The DREQ value for the ADC
+pub const DREQ_ADC: u8 = 36;
+/// The DREQ value for the XIP Streaming FIFO
+pub const DREQ_XIP_STREAM: u8 = 37;
+/// The DREQ value for the XIP SSI TX FIFO
+pub const DREQ_XIP_SSITX: u8 = 38;
+/// The DREQ value for the XIP SSI RX FIFO
+pub const DREQ_XIP_SSIRX: u8 = 39;
@@ -0,0 +1,165 @@
+//! Endless DMA transfers into or out of ring buffers
+//!
+//! A ring transfer continuously moves data between a peripheral and a memory buffer. The DMA
+//! channel wraps the buffer address around once it reaches the end of the buffer, so that the
+//! transfer keeps running without any intervention by the CPU. This can be used to continuously
+//! capture received data, or to continuously repeat the content of a buffer.
+//!
+//! The hardware wraps addresses by keeping the upper address bits constant. Therefore, the buffer
+//! size in bytes needs to be a power of two between 2 and 32768, and the buffer needs to be
+//! aligned to its size:
+//!
+//! ```no_run
+//! use rp2040_hal::dma::{ring, Channel, EndlessReadTarget, ReadTarget, CH0};
+//!
+//! #[repr(C, align(256))]
+//! struct Aligned([u32; 64]);
+//! static mut RING: Aligned = Aligned([0; 64]);
+//!
+//! fn capture<RX>(ch: Channel<CH0>, rx: RX)
+//! where
+//!     RX: ReadTarget<ReceivedWord = u32> + EndlessReadTarget,
+//! {
+//!     // Safety: The buffer is only accessed through this reference.
+//!     let buf: &'static mut [u32; 64] = unsafe { &mut RING.0 };
+//!     let transfer = ring::Config::new(ch, rx, buf).start();
+//!     // The index of the word which is written next.
+//!     let _position = transfer.position();
+//! }
+//! ```
+
+use super::{single_channel::ChannelConfig, Pace, ReadTarget, SingleChannel, WriteTarget};
+use core::mem;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+/// Configuration for an endless ring buffer DMA transfer
+pub struct Config<CH: SingleChannel, FROM: ReadTarget, TO: WriteTarget> {
+    ch: CH,
+    from: FROM,
+    to: TO,
+    pace: Pace,
+}
+
+impl<CH, FROM, TO, WORD> Config<CH, FROM, TO>
+where
+    CH: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Create a new configuration for an endless ring buffer DMA transfer
+    ///
+    /// If `to` is a memory buffer, the received data is written into it as a ring buffer.
+    /// Otherwise, `from` is treated as the ring buffer and its content is repeated endlessly.
+    pub fn new(ch: CH, from: FROM, to: TO) -> Config<CH, FROM, TO> {
+        Config {
+            ch,
+            from,
+            to,
+            pace: Pace::PreferSource,
+        }
+    }
+
+    /// Sets the (preferred) pace for the DMA transfers.
+    ///
+    /// Usually, the code will automatically configure the correct pace, but
+    /// peripheral-to-peripheral transfers require the user to manually select whether the source
+    /// or the sink shall be queried for the pace signal.
+    pub fn pace(&mut self, pace: Pace) {
+        self.pace = pace;
+    }
+
+    /// Start the DMA transfer
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size of the ring buffer in bytes is not a power of two between 2 and 32768,
+    /// or if the buffer is not aligned to its size.
+    pub fn start(mut self) -> Transfer<CH, FROM, TO> {
+        let ring_write = self.to.tx_increment();
+        let (base, count) = if ring_write {
+            self.to.tx_address_count()
+        } else {
+            self.from.rx_address_count()
+        };
+        let size = count * mem::size_of::<WORD>() as u32;
+        assert!(
+            size.is_power_of_two() && (2..=32768).contains(&size),
+            "ring buffer size must be a power of two between 2 and 32768 bytes"
+        );
+        assert!(base % size == 0, "ring buffer must be aligned to its size");
+
+        // Make sure that memory contents reflect what the user intended.
+        cortex_m::asm::dsb();
+        compiler_fence(Ordering::SeqCst);
+
+        // Configure the DMA channel, make it wrap around the buffer and let it run for as long as
+        // possible before starting it.
+        self.ch
+            .config(&self.from, &mut self.to, self.pace, None, false);
+        self.ch.set_ring(ring_write, size.trailing_zeros() as u8);
+        self.ch
+            .ch()
+            .ch_trans_count
+            .write(|w| unsafe { w.bits(u32::MAX) });
+        self.ch.start();
+
+        Transfer {
+            ch: self.ch,
+            from: self.from,
+            to: self.to,
+            ring_write,
+            base,
+        }
+    }
+}
+
+/// Instance of an endless ring buffer DMA transfer
+pub struct Transfer<CH: SingleChannel, FROM: ReadTarget, TO: WriteTarget> {
+    ch: CH,
+    from: FROM,
+    to: TO,
+    ring_write: bool,
+    base: u32,
+}
+
+impl<CH, FROM, TO, WORD> Transfer<CH, FROM, TO>
+where
+    CH: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Returns the index of the word in the ring buffer which the DMA channel accesses next.
+    pub fn position(&self) -> usize {
+        let address = if self.ring_write {
+            self.ch.ch().ch_write_addr.read().bits()
+        } else {
+            self.ch.ch().ch_read_addr.read().bits()
+        };
+        (address - self.base) as usize / mem::size_of::<WORD>()
+    }
+
+    /// Restarts the channel if it has stopped.
+    ///
+    /// The channel stops after transferring `u32::MAX` words, which for a peripheral-paced
+    /// transfer takes hours. Calling this function regularly keeps the transfer running for
+    /// good. The transfer continues at the current position in the ring buffer.
+    pub fn rearm(&mut self) {
+        if !self.ch.is_busy() {
+            self.ch
+                .ch()
+                .ch_al1_trans_count_trig
+                .write(|w| unsafe { w.bits(u32::MAX) });
+        }
+    }
+
+    /// Stops the transfer, returning the channel and the buffers.
+    pub fn stop(mut self) -> (CH, FROM, TO) {
+        self.ch.abort();
+
+        // Make sure that memory contents reflect what the user intended.
+        cortex_m::asm::dsb();
+        compiler_fence(Ordering::SeqCst);
+
+        (self.ch, self.from, self.to)
+    }
+}
@@ -0,0 +1,248 @@
+use super::{Channel, ChannelIndex, Pace, ReadTarget, WriteTarget, TREQ_UNPACED};
+use core::mem;
+use rp2040_pac::DMA;
+
+/// Trait which is implemented by anything that can be used as a single DMA channel.
+///
+/// This trait gives access to the channel's registers and interrupt flags. Most of its methods are
+/// only useful for users who want to configure a transfer manually.
+pub trait SingleChannel: Sealed {
+    /// Returns the registers associated with this DMA channel.
+    ///
+    /// In the case of channel pairs, this returns the first channel.
+    fn ch(&self) -> &rp2040_pac::dma::CH;
+
+    /// Returns the index of the DMA channel.
+    fn id(&self) -> u8;
+
+    /// Enables the DMA_IRQ_0 signal for this channel.
+    fn listen_irq0(&mut self) {
+        // Safety: We only use the atomic alias of the register.
+        unsafe {
+            crate::atomic_register_access::write_bitmask_set(
+                (*DMA::ptr()).inte0.as_ptr(),
+                1 << self.id(),
+            );
+        }
+    }
+
+    /// Disables the DMA_IRQ_0 signal for this channel.
+    fn unlisten_irq0(&mut self) {
+        // Safety: We only use the atomic alias of the register.
+        unsafe {
+            crate::atomic_register_access::write_bitmask_clear(
+                (*DMA::ptr()).inte0.as_ptr(),
+                1 << self.id(),
+            );
+        }
+    }
+
+    /// Enables the DMA_IRQ_1 signal for this channel.
+    fn listen_irq1(&mut self) {
+        // Safety: We only use the atomic alias of the register.
+        unsafe {
+            crate::atomic_register_access::write_bitmask_set(
+                (*DMA::ptr()).inte1.as_ptr(),
+                1 << self.id(),
+            );
+        }
+    }
+
+    /// Disables the DMA_IRQ_1 signal for this channel.
+    fn unlisten_irq1(&mut self) {
+        // Safety: We only use the atomic alias of the register.
+        unsafe {
+            crate::atomic_register_access::write_bitmask_clear(
+                (*DMA::ptr()).inte1.as_ptr(),
+                1 << self.id(),
+            );
+        }
+    }
+
+    /// Check if an interrupt is pending for this channel on DMA_IRQ_0 and clear the corresponding
+    /// pending bit.
+    fn check_irq0(&mut self) -> bool {
+        // Safety: The write does not interfere with any other writes, it only affects this
+        // channel.
+        let dma = unsafe { &*DMA::ptr() };
+        let status = dma.ints0.read().bits();
+        if (status & (1 << self.id())) != 0 {
+            // Clear the interrupt.
+            dma.ints0.write(|w| unsafe { w.bits(1 << self.id()) });
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Check if an interrupt is pending for this channel on DMA_IRQ_1 and clear the corresponding
+    /// pending bit.
+    fn check_irq1(&mut self) -> bool {
+        // Safety: The write does not interfere with any other writes, it only affects this
+        // channel.
+        let dma = unsafe { &*DMA::ptr() };
+        let status = dma.ints1.read().bits();
+        if (status & (1 << self.id())) != 0 {
+            // Clear the interrupt.
+            dma.ints1.write(|w| unsafe { w.bits(1 << self.id()) });
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns whether the channel is currently transferring data.
+    fn is_busy(&self) -> bool {
+        self.ch().ch_ctrl_trig.read().busy().bit_is_set()
+    }
+
+    /// Aborts the current transfer of this channel.
+    ///
+    /// This blocks until the channel has stopped.
+    fn abort(&mut self) {
+        // Safety: The abort register is write-1-to-set, so other channels are not affected.
+        let dma = unsafe { &*DMA::ptr() };
+        dma.chan_abort.write(|w| unsafe { w.bits(1 << self.id()) });
+        while dma.chan_abort.read().bits() & (1 << self.id()) != 0 {}
+    }
+}
+
+impl<CH: ChannelIndex> SingleChannel for Channel<CH> {
+    fn ch(&self) -> &rp2040_pac::dma::CH {
+        // Safety: The channel registers are exclusively owned by this `Channel`.
+        unsafe { &(*DMA::ptr()).ch[CH::id() as usize] }
+    }
+
+    fn id(&self) -> u8 {
+        CH::id()
+    }
+}
+
+impl<CH: ChannelIndex> Sealed for Channel<CH> {}
+
+pub trait Sealed {}
+
+/// Channel configuration shared by all transfer types.
+pub(crate) trait ChannelConfig {
+    /// Configures the channel to copy from `from` to `to`.
+    ///
+    /// If `chain_to` is `None`, the channel chains to itself, which disables chaining. The
+    /// channel is triggered right away if `start` is true.
+    fn config<WORD, FROM, TO>(
+        &mut self,
+        from: &FROM,
+        to: &mut TO,
+        pace: Pace,
+        chain_to: Option<u8>,
+        start: bool,
+    ) where
+        FROM: ReadTarget<ReceivedWord = WORD>,
+        TO: WriteTarget<TransmittedWord = WORD>;
+
+    /// Makes the channel wrap the read (`write == false`) or write (`write == true`) address
+    /// around a naturally aligned ring buffer of `1 << size_bits` bytes.
+    ///
+    /// Has to be called after `config()` and before the channel is started.
+    fn set_ring(&mut self, write: bool, size_bits: u8);
+
+    /// Chains this channel to `other`, so that `other` is triggered once this channel has
+    /// completed.
+    ///
+    /// If this channel has already completed, `other` is triggered right away.
+    fn set_chain_to_enabled<CH: SingleChannel>(&mut self, other: &mut CH);
+
+    /// Triggers the previously configured transfer.
+    fn start(&mut self);
+
+    /// Triggers the previously configured transfers of this channel and `other` at the same time.
+    fn start_both<CH: SingleChannel>(&mut self, other: &mut CH);
+}
+
+impl<CH: SingleChannel> ChannelConfig for CH {
+    fn config<WORD, FROM, TO>(
+        &mut self,
+        from: &FROM,
+        to: &mut TO,
+        pace: Pace,
+        chain_to: Option<u8>,
+        start: bool,
+    ) where
+        FROM: ReadTarget<ReceivedWord = WORD>,
+        TO: WriteTarget<TransmittedWord = WORD>,
+    {
+        let (src, src_count) = from.rx_address_count();
+        let src_incr = from.rx_increment();
+        let (dest, dest_count) = to.tx_address_count();
+        let dest_incr = to.tx_increment();
+        let treq = match pace {
+            Pace::PreferSource => from.rx_treq().or_else(|| to.tx_treq()),
+            Pace::PreferSink => to.tx_treq().or_else(|| from.rx_treq()),
+        }
+        .unwrap_or(TREQ_UNPACED);
+        let len = u32::min(src_count, dest_count);
+        let id = self.id();
+
+        // Write the control register through the non-triggering alias, then the addresses and the
+        // count. The write address is written last, through its triggering alias if requested.
+        self.ch().ch_al1_ctrl.write(|w| unsafe {
+            w.data_size()
+                .bits(mem::size_of::<WORD>() as u8 >> 1)
+                .incr_read()
+                .bit(src_incr)
+                .incr_write()
+                .bit(dest_incr)
+                .treq_sel()
+                .bits(treq)
+                .chain_to()
+                .bits(chain_to.unwrap_or(id))
+                .en()
+                .set_bit()
+        });
+        self.ch().ch_read_addr.write(|w| unsafe { w.bits(src) });
+        self.ch().ch_trans_count.write(|w| unsafe { w.bits(len) });
+        if start {
+            self.ch()
+                .ch_al2_write_addr_trig
+                .write(|w| unsafe { w.bits(dest) });
+        } else {
+            self.ch().ch_write_addr.write(|w| unsafe { w.bits(dest) });
+        }
+    }
+
+    fn set_ring(&mut self, write: bool, size_bits: u8) {
+        self.ch()
+            .ch_al1_ctrl
+            .modify(|_, w| unsafe { w.ring_sel().bit(write).ring_size().bits(size_bits) });
+    }
+
+    fn set_chain_to_enabled<CH: SingleChannel>(&mut self, other: &mut CH) {
+        // We temporarily pause the channel when setting CHAIN_TO, to prevent any race condition
+        // that could cause the other channel to be started but then mis-chained.
+        self.ch().ch_al1_ctrl.modify(|_, w| w.en().clear_bit());
+        self.ch()
+            .ch_al1_ctrl
+            .modify(|_, w| unsafe { w.chain_to().bits(other.id()).en().set_bit() });
+        if self.ch().ch_al1_ctrl.read().busy().bit_is_clear() {
+            // The transfer had already ended before the chain was set up, so the other channel
+            // has to be started manually. Triggering a busy channel has no effect, so this is
+            // harmless if the chain fired in the meantime.
+            other.start();
+        }
+    }
+
+    fn start(&mut self) {
+        // Safety: The write does not interfere with any other writes, it only affects this
+        // channel.
+        unsafe { &*DMA::ptr() }
+            .multi_chan_trigger
+            .write(|w| unsafe { w.bits(1 << self.id()) });
+    }
+
+    fn start_both<CH: SingleChannel>(&mut self, other: &mut CH) {
+        // Safety: The write does not interfere with any other writes, it only affects these two
+        // channels.
+        unsafe { &*DMA::ptr() }
+            .multi_chan_trigger
+            .write(|w| unsafe { w.bits((1 << self.id()) | (1 << other.id())) });
+    }
+}
@@ -0,0 +1,185 @@
+//! Flash storage
+//!
+//! Erase and program the QSPI flash, for example to keep configuration data in the last sectors.
+//! [`Flash`] implements the [`embedded-storage`](https://docs.rs/embedded-storage) `NorFlash`
+//! and `ReadNorFlash` traits, with offsets relative to the start of the flash.
+//!
+//! While the flash is being erased or programmed it can not be used to execute code. The flash
+//! operations therefore run from RAM with interrupts disabled, and the other core is locked out
+//! using the [multicore lockout](crate::multicore#lockout):
+//!
+//! ```no_run
+//! use embedded_storage::nor_flash::{NorFlash, ReadNorFlash};
+//! use rp2040_hal::{flash::Flash, pac, sio::Sio};
+//!
+//! const FLASH_SIZE: usize = 2 * 1024 * 1024;
+//! const CONFIG_OFFSET: u32 = (FLASH_SIZE - 4096) as u32;
+//!
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let mut sio = Sio::new(peripherals.SIO);
+//! let mut flash = Flash::with_lockout(peripherals.XIP_SSI, FLASH_SIZE, &mut sio.fifo);
+//!
+//! let mut config = [0; 256];
+//! flash.read(CONFIG_OFFSET, &mut config).unwrap();
+//! config[0] = config[0].wrapping_add(1);
+//! flash.erase(CONFIG_OFFSET, CONFIG_OFFSET + 4096).unwrap();
+//! flash.write(CONFIG_OFFSET, &config).unwrap();
+//! ```
+
+use crate::multicore;
+use crate::sio::SioFifo;
+use crate::ssi::Ssi;
+use core::ptr::read_volatile;
+use embedded_storage::nor_flash::{
+    ErrorType, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash,
+};
+
+/// Start of the XIP address window
+const XIP_BASE: usize = 0x1000_0000;
+
+/// Size of the erasable sectors
+const SECTOR_SIZE: usize = 4096;
+/// Size of the programmable pages
+const PAGE_SIZE: usize = 256;
+
+/// Flash errors
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The offset or length is not aligned to the sector or page size
+    NotAligned,
+    /// The range is outside of the flash
+    OutOfBounds,
+}
+
+impl NorFlashError for Error {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            Error::NotAligned => NorFlashErrorKind::NotAligned,
+            Error::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+        }
+    }
+}
+
+/// Erase and program the QSPI flash
+pub struct Flash<'a> {
+    ssi: Ssi,
+    size: usize,
+    fifo: Option<&'a mut SioFifo>,
+}
+
+impl Flash<'static> {
+    /// Create a new flash driver for a flash chip of `size` bytes, without locking out the other
+    /// core.
+    ///
+    /// # Safety
+    ///
+    /// The other core must not access the flash while it is being erased or programmed, e.g.
+    /// because it has not been started, or is running from RAM.
+    pub unsafe fn new(device: pac::XIP_SSI, size: usize) -> Self {
+        Self {
+            ssi: Ssi::new(device),
+            size,
+            fifo: None,
+        }
+    }
+}
+
+impl<'a> Flash<'a> {
+    /// Create a new flash driver for a flash chip of `size` bytes, which locks out the other core
+    /// during erase and program operations.
+    ///
+    /// The other core must be set up as a lockout victim, see
+    /// [`lockout_start_blocking`](multicore::lockout_start_blocking).
+    pub fn with_lockout(device: pac::XIP_SSI, size: usize, fifo: &'a mut SioFifo) -> Self {
+        Self {
+            ssi: Ssi::new(device),
+            size,
+            fifo: Some(fifo),
+        }
+    }
+
+    /// Free the underlying device.
+    pub fn free(self) -> pac::XIP_SSI {
+        self.ssi.free()
+    }
+
+    fn check_range(&self, offset: u32, len: usize) -> Result<(), Error> {
+        match (offset as usize).checked_add(len) {
+            Some(end) if end <= self.size => Ok(()),
+            _ => Err(Error::OutOfBounds),
+        }
+    }
+
+    /// Run `f` with the other core locked out, if a FIFO was given.
+    fn locked_out<R>(&mut self, f: impl FnOnce(&mut Ssi) -> R) -> R {
+        let ssi = &mut self.ssi;
+        match self.fifo.as_mut() {
+            Some(fifo) => {
+                multicore::lockout_start_blocking(fifo);
+                let result = f(ssi);
+                multicore::lockout_end_blocking(fifo);
+                result
+            }
+            None => f(ssi),
+        }
+    }
+}
+
+impl<'a> ErrorType for Flash<'a> {
+    type Error = Error;
+}
+
+impl<'a> ReadNorFlash for Flash<'a> {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        self.check_range(offset, bytes.len())?;
+        let src = (XIP_BASE + offset as usize) as *const u8;
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            // Safety: The range was checked to be within the flash, which is mapped at XIP_BASE.
+            *byte = unsafe { read_volatile(src.add(i)) };
+        }
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.size
+    }
+}
+
+impl<'a> NorFlash for Flash<'a> {
+    const WRITE_SIZE: usize = PAGE_SIZE;
+    const ERASE_SIZE: usize = SECTOR_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        if from > to {
+            return Err(Error::OutOfBounds);
+        }
+        let len = (to - from) as usize;
+        self.check_range(from, len)?;
+        if from as usize % SECTOR_SIZE != 0 || len % SECTOR_SIZE != 0 {
+            return Err(Error::NotAligned);
+        }
+        // Safety: Interrupts are disabled by the SSI driver and the other core is locked out if
+        // requested. Erasing the flash in use is the caller's responsibility.
+        self.locked_out(|ssi| unsafe { ssi.flash_range_erase(from, len) });
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.check_range(offset, bytes.len())?;
+        if offset as usize % PAGE_SIZE != 0 || bytes.len() % PAGE_SIZE != 0 {
+            return Err(Error::NotAligned);
+        }
+        // The data could be located in flash, so copy it to RAM one page at a time.
+        let mut page = [0; PAGE_SIZE];
+        for (i, chunk) in bytes.chunks(PAGE_SIZE).enumerate() {
+            page.copy_from_slice(chunk);
+            let addr = offset + (i * PAGE_SIZE) as u32;
+            // Safety: See `erase`. `page` is located on the stack.
+            self.locked_out(|ssi| unsafe { ssi.flash_range_program(addr, &page) });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+//! Waiting for input pin levels and edges with `async`/`await`
+//!
+//! Requires the `async` feature. The waiting futures enable the matching GPIO interrupt for the
+//! pin and are woken from [`handle_async_interrupt`], which must be called from the
+//! `IO_IRQ_BANK0` interrupt handler. The interrupt also has to be unmasked in the NVIC.
+//!
+//! ```ignore
+//! use rp2040_hal::{gpio, pac::interrupt};
+//!
+//! #[interrupt]
+//! fn IO_IRQ_BANK0() {
+//!     gpio::handle_async_interrupt();
+//! }
+//!
+//! async fn wait_for_button(button: &mut gpio::Pin<gpio::bank0::Gpio23, gpio::PullUpInput>) {
+//!     button.wait_for_falling_edge().await;
+//! }
+//! ```
+//!
+//! Pins which are not currently awaited are left alone by [`handle_async_interrupt`], so it can
+//! be combined with handling other GPIO interrupts manually.
+
+use super::bank0::BankPinId;
+use super::{Input, InputConfig, Interrupt, Pin, PinId};
+use crate::async_utils::WakerCell;
+use crate::atomic_register_access::write_bitmask_clear;
+use core::future::Future;
+use core::pin::Pin as FuturePin;
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::task::{Context, Poll};
+use embedded_hal::digital::v2::InputPin;
+
+const NUM_PINS: usize = 30;
+
+const NO_WAKER: WakerCell = WakerCell::new();
+static WAKERS: [WakerCell; NUM_PINS] = [NO_WAKER; NUM_PINS];
+
+/// Bitmask of the pins which have a waiting future. Only modified within critical sections.
+static WAITING: AtomicU32 = AtomicU32::new(0);
+
+/// Wake the futures waiting for a GPIO interrupt.
+///
+/// Call this from the `IO_IRQ_BANK0` interrupt handler. The interrupts of the pins that were
+/// waited on are disabled again, so that level interrupts do not keep firing.
+pub fn handle_async_interrupt() {
+    critical_section::with(|_| {
+        let waiting = WAITING.load(Ordering::Relaxed);
+        if waiting == 0 {
+            return;
+        }
+        // Safety: Only the interrupt enables of pins owned by a waiting future are modified.
+        let io = unsafe { &*pac::IO_BANK0::ptr() };
+        let core1 = crate::Sio::core() == 1;
+        let mut woken = 0;
+        for (num, waker) in WAKERS.iter().enumerate() {
+            if waiting & (1 << num) == 0 {
+                continue;
+            }
+            // There are four bits for each GPIO pin (one for each enumerator
+            // in the `Interrupt` enum). There are therefore eight pins per
+            // 32-bit register, and four registers per CPU.
+            let shift = num % 8 * 4;
+            let (ints, inte) = if core1 {
+                (
+                    io.proc1_ints[num >> 3].read().bits(),
+                    io.proc1_inte[num >> 3].as_ptr(),
+                )
+            } else {
+                (
+                    io.proc0_ints[num >> 3].read().bits(),
+                    io.proc0_inte[num >> 3].as_ptr(),
+                )
+            };
+            if ints & (0xf << shift) != 0 {
+                unsafe { write_bitmask_clear(inte, 0xf << shift) };
+                woken |= 1 << num;
+                waker.wake();
+            }
+        }
+        WAITING.store(waiting & !woken, Ordering::Relaxed);
+    });
+}
+
+/// Future returned by the `wait_for_*` methods of input pins.
+struct InterruptFuture<'a, I, C>
+where
+    I: PinId + BankPinId,
+    C: InputConfig,
+{
+    pin: &'a mut Pin<I, Input<C>>,
+    interrupts: &'static [Interrupt],
+    armed: bool,
+}
+
+impl<'a, I, C> InterruptFuture<'a, I, C>
+where
+    I: PinId + BankPinId,
+    C: InputConfig,
+{
+    const MASK: u32 = 1 << I::DYN.num;
+
+    fn new(pin: &'a mut Pin<I, Input<C>>, interrupts: &'static [Interrupt]) -> Self {
+        Self {
+            pin,
+            interrupts,
+            armed: false,
+        }
+    }
+
+    fn waker(&self) -> &'static WakerCell {
+        &WAKERS[I::DYN.num as usize]
+    }
+}
+
+impl<'a, I, C> Future for InterruptFuture<'a, I, C>
+where
+    I: PinId + BankPinId,
+    C: InputConfig,
+{
+    type Output = ();
+
+    fn poll(self: FuturePin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        critical_section::with(|_| {
+            if this.armed {
+                if WAITING.load(Ordering::Relaxed) & Self::MASK == 0 {
+                    this.armed = false;
+                    return Poll::Ready(());
+                }
+                this.waker().register(cx.waker());
+                return Poll::Pending;
+            }
+            this.waker().register(cx.waker());
+            for &interrupt in this.interrupts {
+                // Discard edges which happened before we started waiting.
+                this.pin.clear_interrupt(interrupt);
+                this.pin.set_interrupt_enabled(interrupt, true);
+            }
+            WAITING.store(
+                WAITING.load(Ordering::Relaxed) | Self::MASK,
+                Ordering::Relaxed,
+            );
+            this.armed = true;
+            Poll::Pending
+        })
+    }
+}
+
+impl<'a, I, C> Drop for InterruptFuture<'a, I, C>
+where
+    I: PinId + BankPinId,
+    C: InputConfig,
+{
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        critical_section::with(|_| {
+            for &interrupt in self.interrupts {
+                self.pin.set_interrupt_enabled(interrupt, false);
+            }
+            WAITING.store(
+                WAITING.load(Ordering::Relaxed) & !Self::MASK,
+                Ordering::Relaxed,
+            );
+        });
+    }
+}
+
+impl<I, C> Pin<I, Input<C>>
+where
+    I: PinId + BankPinId,
+    C: InputConfig,
+{
+    /// Wait until the pin is high.
+    ///
+    /// Returns immediately if the pin is already high.
+    pub async fn wait_for_high(&mut self) {
+        if let Ok(true) = self.is_high() {
+            return;
+        }
+        InterruptFuture::new(self, &[Interrupt::LevelHigh]).await
+    }
+
+    /// Wait until the pin is low.
+    ///
+    /// Returns immediately if the pin is already low.
+    pub async fn wait_for_low(&mut self) {
+        if let Ok(true) = self.is_low() {
+            return;
+        }
+        InterruptFuture::new(self, &[Interrupt::LevelLow]).await
+    }
+
+    /// Wait for the next transition from low to high.
+    pub async fn wait_for_rising_edge(&mut self) {
+        InterruptFuture::new(self, &[Interrupt::EdgeHigh]).await
+    }
+
+    /// Wait for the next transition from high to low.
+    pub async fn wait_for_falling_edge(&mut self) {
+        InterruptFuture::new(self, &[Interrupt::EdgeLow]).await
+    }
+
+    /// Wait for the next transition in either direction.
+    pub async fn wait_for_any_edge(&mut self) {
+        InterruptFuture::new(self, &[Interrupt::EdgeHigh, Interrupt::EdgeLow]).await
+    }
+}
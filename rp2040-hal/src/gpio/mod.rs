@@ -41,6 +41,11 @@ pub use dynpin::*;
 
 mod reg;
 
+#[cfg(feature = "async")]
+pub mod async_pin;
+#[cfg(feature = "async")]
+pub use async_pin::handle_async_interrupt;
+
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
 /// The amount of current that a pin can drive when used as an output
 pub enum OutputDriveStrength {
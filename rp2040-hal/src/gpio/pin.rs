@@ -599,6 +599,21 @@ where
         self.regs.set_interrupt_forced(interrupt, forced);
     }
 
+    /// Is waking the chip from dormant mode on `interrupt` enabled.
+    #[inline]
+    pub fn is_dormant_wake_enabled(&self, interrupt: Interrupt) -> bool {
+        self.regs.is_dormant_wake_enabled(interrupt)
+    }
+
+    /// Enable or disable waking the chip from dormant mode on `interrupt`.
+    ///
+    /// Edge events are latched, clear them with [`clear_interrupt`](Self::clear_interrupt)
+    /// before entering dormant mode. See [`crate::power`].
+    #[inline]
+    pub fn set_dormant_wake_enabled(&self, interrupt: Interrupt, enabled: bool) {
+        self.regs.set_dormant_wake_enabled(interrupt, enabled);
+    }
+
     /// Set the interrupt override.
     #[inline]
     pub fn set_interrupt_override(&mut self, override_value: InterruptOverride) {
@@ -382,6 +382,39 @@ pub(super) unsafe trait RegisterInterface {
         }
     }
 
+    /// Is dormant wake enabled.
+    #[inline]
+    fn is_dormant_wake_enabled(&self, interrupt: Interrupt) -> bool {
+        let num = self.id().num as usize;
+        unsafe {
+            let io = &(*pac::IO_BANK0::ptr());
+            // The dormant wake registers use the same layout as the
+            // interrupt registers, four bits per pin and eight pins per
+            // register.
+            let bit_in_reg = num % 8 * 4 + interrupt as usize;
+            (io.dormant_wake_inte[num >> 3].read().bits() & (1 << bit_in_reg)) != 0
+        }
+    }
+
+    /// Enable or disable waking the chip from dormant mode.
+    #[inline]
+    fn set_dormant_wake_enabled(&self, interrupt: Interrupt, enabled: bool) {
+        let num = self.id().num as usize;
+        unsafe {
+            let io = &(*pac::IO_BANK0::ptr());
+            // The dormant wake registers use the same layout as the
+            // interrupt registers, four bits per pin and eight pins per
+            // register.
+            let reg = io.dormant_wake_inte[num >> 3].as_ptr();
+            let bit_in_reg = num % 8 * 4 + interrupt as usize;
+            if enabled {
+                write_bitmask_set(reg, 1 << bit_in_reg);
+            } else {
+                write_bitmask_clear(reg, 1 << bit_in_reg);
+            }
+        }
+    }
+
     /// Set the interrupt override.
     #[inline]
     fn set_interrupt_override(&self, override_value: InterruptOverride) {
@@ -60,6 +60,8 @@ use pac::{i2c0::RegisterBlock as I2CBlock, I2C0, I2C1, RESETS};
 
 /// Controller implementaion
 pub mod controller;
+#[cfg(feature = "async")]
+pub use controller::handle_async_interrupt;
 /// Peripheral implementation
 pub mod peripheral;
 
@@ -156,6 +158,19 @@ impl SclPin<I2C1> for Gpio27 {}
 impl SdaPin<I2C0> for Gpio28 {}
 impl SclPin<I2C0> for Gpio29 {}
 
+/// Pac I2C device
+pub trait I2CDevice: Deref<Target = I2CBlock> + SubsystemReset {
+    /// Index of this device, 0 for I2C0 and 1 for I2C1
+    const ID: usize;
+}
+
+impl I2CDevice for I2C0 {
+    const ID: usize = 0;
+}
+impl I2CDevice for I2C1 {
+    const ID: usize = 1;
+}
+
 /// Operational mode of the I2C peripheral.
 pub trait I2CMode: Sealed {
     /// Indicates whether this mode is Controller or Peripheral.
@@ -14,6 +14,11 @@ use eh1_0_alpha::i2c as eh1;
 
 use super::{i2c_reserved_addr, Controller, Error, SclPin, SdaPin, I2C};
 
+#[cfg(feature = "async")]
+use super::I2CDevice;
+#[cfg(feature = "async")]
+use crate::async_utils::{wait_until, WakerCell};
+
 impl<T: SubsystemReset + Deref<Target = Block>, Sda: PinId + BankPinId, Scl: PinId + BankPinId>
     I2C<T, (Pin<Sda, FunctionI2C>, Pin<Scl, FunctionI2C>), Controller>
 {
@@ -157,6 +162,35 @@ impl<T: Deref<Target = Block>, PINS> I2C<T, PINS, Controller> {
         }
     }
 
+    fn push_read_cmd(&mut self, restart: bool, stop: bool) {
+        self.i2c.ic_data_cmd.write(|w| {
+            if restart {
+                w.restart().enable();
+            } else {
+                w.restart().disable();
+            }
+
+            if stop {
+                w.stop().enable();
+            } else {
+                w.stop().disable();
+            }
+
+            w.cmd().read()
+        });
+    }
+
+    fn push_write_cmd(&mut self, byte: u8, stop: bool) {
+        self.i2c.ic_data_cmd.write(|w| {
+            if stop {
+                w.stop().enable();
+            } else {
+                w.stop().disable();
+            }
+            unsafe { w.dat().bits(byte) }
+        });
+    }
+
     fn read_internal(
         &mut self,
         buffer: &mut [u8],
@@ -171,21 +205,7 @@ impl<T: Deref<Target = Block>, PINS> I2C<T, PINS, Controller> {
             // wait until there is space in the FIFO to write the next byte
             while self.tx_fifo_full() {}
 
-            self.i2c.ic_data_cmd.write(|w| {
-                if force_restart && first {
-                    w.restart().enable();
-                } else {
-                    w.restart().disable();
-                }
-
-                if do_stop && last {
-                    w.stop().enable();
-                } else {
-                    w.stop().disable();
-                }
-
-                w.cmd().read()
-            });
+            self.push_read_cmd(force_restart && first, do_stop && last);
 
             while self.i2c.ic_rxflr.read().bits() == 0 {
                 if let Some(abort_reason) = self.read_and_clear_abort_reason() {
@@ -203,14 +223,7 @@ impl<T: Deref<Target = Block>, PINS> I2C<T, PINS, Controller> {
         for (i, byte) in bytes.iter().enumerate() {
             let last = i == bytes.len() - 1;
 
-            self.i2c.ic_data_cmd.write(|w| {
-                if do_stop && last {
-                    w.stop().enable();
-                } else {
-                    w.stop().disable();
-                }
-                unsafe { w.dat().bits(*byte) }
-            });
+            self.push_write_cmd(*byte, do_stop && last);
 
             // Wait until the transmission of the address/data from the internal
             // shift register has completed. For this to function correctly, the
@@ -239,6 +252,233 @@ impl<T: Deref<Target = Block>, PINS> I2C<T, PINS, Controller> {
         Ok(())
     }
 }
+/// IC_INTR_MASK bit of the RX_FULL interrupt
+#[cfg(feature = "async")]
+const INTR_RX_FULL: u32 = 1 << 2;
+/// IC_INTR_MASK bit of the TX_EMPTY interrupt
+#[cfg(feature = "async")]
+const INTR_TX_EMPTY: u32 = 1 << 4;
+/// IC_INTR_MASK bit of the TX_ABRT interrupt
+#[cfg(feature = "async")]
+const INTR_TX_ABRT: u32 = 1 << 6;
+/// IC_INTR_MASK bit of the STOP_DET interrupt
+#[cfg(feature = "async")]
+const INTR_STOP_DET: u32 = 1 << 9;
+
+#[cfg(feature = "async")]
+static WAKERS: [WakerCell; 2] = [WakerCell::new(), WakerCell::new()];
+
+/// Wake the task waiting in one of the async transfers of an [`I2C`] controller.
+///
+/// Call this from the I2Cx IRQ handler of the device `T`, e.g.
+/// `handle_async_interrupt::<pac::I2C0>()`. All I2C interrupts are masked until the next time
+/// the task has to wait.
+#[cfg(feature = "async")]
+pub fn handle_async_interrupt<T: I2CDevice>() {
+    // Safety: Only the interrupt mask is modified, which the waiting task sets up again.
+    let rb = unsafe {
+        &*if T::ID == 0 {
+            pac::I2C0::ptr()
+        } else {
+            pac::I2C1::ptr()
+        }
+    };
+    rb.ic_intr_mask.write(|w| unsafe { w.bits(0) });
+    WAKERS[T::ID].wake();
+}
+
+#[cfg(feature = "async")]
+impl<T: I2CDevice, PINS> I2C<T, PINS, Controller> {
+    /// Wait until `ready` returns `true`, unmasking the interrupts in `mask` while waiting.
+    async fn wait_for(&self, ready: impl Fn(&Block) -> bool, mask: u32) {
+        let i2c = &self.i2c;
+        wait_until(
+            &WAKERS[T::ID],
+            || ready(i2c),
+            || i2c.ic_intr_mask.write(|w| unsafe { w.bits(mask) }),
+        )
+        .await
+    }
+
+    async fn read_internal_async(
+        &mut self,
+        buffer: &mut [u8],
+        force_restart: bool,
+        do_stop: bool,
+    ) -> Result<(), Error> {
+        let lastindex = buffer.len() - 1;
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            let first = i == 0;
+            let last = i == lastindex;
+
+            // Every byte is read before the next one is requested, so there is always space in
+            // the TX FIFO.
+            self.push_read_cmd(force_restart && first, do_stop && last);
+
+            self.wait_for(
+                |i2c| i2c.ic_rxflr.read().bits() != 0 || i2c.ic_tx_abrt_source.read().bits() != 0,
+                INTR_RX_FULL | INTR_TX_ABRT,
+            )
+            .await;
+            if let Some(abort_reason) = self.read_and_clear_abort_reason() {
+                return Err(Error::Abort(abort_reason));
+            }
+
+            *byte = self.i2c.ic_data_cmd.read().dat().bits();
+        }
+
+        Ok(())
+    }
+
+    async fn write_internal_async(&mut self, bytes: &[u8], do_stop: bool) -> Result<(), Error> {
+        for (i, byte) in bytes.iter().enumerate() {
+            let last = i == bytes.len() - 1;
+
+            self.push_write_cmd(*byte, do_stop && last);
+
+            // See `write_internal`.
+            self.wait_for(
+                |i2c| i2c.ic_raw_intr_stat.read().tx_empty().is_active(),
+                INTR_TX_EMPTY,
+            )
+            .await;
+
+            let abort_reason = self.read_and_clear_abort_reason();
+
+            if abort_reason.is_some() || (do_stop && last) {
+                self.wait_for(
+                    |i2c| i2c.ic_raw_intr_stat.read().stop_det().is_active(),
+                    INTR_STOP_DET,
+                )
+                .await;
+
+                self.i2c.ic_clr_stop_det.read().clr_stop_det();
+            }
+
+            if let Some(abort_reason) = abort_reason {
+                return Err(Error::Abort(abort_reason));
+            }
+        }
+        Ok(())
+    }
+
+    /// Read from the device at `addr` into `buffer`.
+    ///
+    /// [`handle_async_interrupt`] must be called from the I2Cx IRQ handler.
+    pub async fn read_async(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Error> {
+        let addr: u16 = addr.into();
+        Self::validate(addr, None, Some(buffer.is_empty()))?;
+
+        self.setup(addr);
+        self.read_internal_async(buffer, true, true).await
+    }
+
+    /// Write `bytes` to the device at `addr`.
+    ///
+    /// [`handle_async_interrupt`] must be called from the I2Cx IRQ handler.
+    pub async fn write_async(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Error> {
+        let addr: u16 = addr.into();
+        Self::validate(addr, Some(bytes.is_empty()), None)?;
+
+        self.setup(addr);
+        self.write_internal_async(bytes, true).await
+    }
+
+    /// Write `bytes` to the device at `addr`, then read into `buffer` after a repeated start.
+    ///
+    /// [`handle_async_interrupt`] must be called from the I2Cx IRQ handler.
+    pub async fn write_read_async(
+        &mut self,
+        addr: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Error> {
+        let addr: u16 = addr.into();
+        Self::validate(addr, Some(bytes.is_empty()), Some(buffer.is_empty()))?;
+
+        self.setup(addr);
+        self.write_internal_async(bytes, false).await?;
+        self.read_internal_async(buffer, true, true).await
+    }
+}
+
+#[cfg(feature = "eh1_0_async")]
+impl<T: I2CDevice, PINS> embedded_hal_async::i2c::I2c for I2C<T, PINS, Controller> {
+    type ReadFuture<'a>
+        = impl core::future::Future<Output = Result<(), Error>> + 'a
+    where
+        Self: 'a;
+
+    fn read<'a>(&'a mut self, address: u8, buffer: &'a mut [u8]) -> Self::ReadFuture<'a> {
+        self.read_async(address, buffer)
+    }
+
+    type WriteFuture<'a>
+        = impl core::future::Future<Output = Result<(), Error>> + 'a
+    where
+        Self: 'a;
+
+    fn write<'a>(&'a mut self, address: u8, bytes: &'a [u8]) -> Self::WriteFuture<'a> {
+        self.write_async(address, bytes)
+    }
+
+    type WriteReadFuture<'a>
+        = impl core::future::Future<Output = Result<(), Error>> + 'a
+    where
+        Self: 'a;
+
+    fn write_read<'a>(
+        &'a mut self,
+        address: u8,
+        bytes: &'a [u8],
+        buffer: &'a mut [u8],
+    ) -> Self::WriteReadFuture<'a> {
+        self.write_read_async(address, bytes, buffer)
+    }
+
+    type TransactionFuture<'a, 'b>
+        = impl core::future::Future<Output = Result<(), Error>> + 'a
+    where
+        Self: 'a,
+        'b: 'a;
+
+    fn transaction<'a, 'b>(
+        &'a mut self,
+        address: u8,
+        operations: &'a mut [embedded_hal_async::i2c::Operation<'b>],
+    ) -> Self::TransactionFuture<'a, 'b> {
+        async move {
+            let addr: u16 = address.into();
+            Self::validate(addr, None, None)?;
+            for operation in operations.iter() {
+                match operation {
+                    embedded_hal_async::i2c::Operation::Read(buf) => {
+                        Self::validate(addr, None, Some(buf.is_empty()))?
+                    }
+                    embedded_hal_async::i2c::Operation::Write(buf) => {
+                        Self::validate(addr, Some(buf.is_empty()), None)?
+                    }
+                }
+            }
+
+            self.setup(addr);
+            let count = operations.len();
+            for (i, operation) in operations.iter_mut().enumerate() {
+                let last = i == count - 1;
+                match operation {
+                    embedded_hal_async::i2c::Operation::Read(buf) => {
+                        self.read_internal_async(buf, false, last).await?
+                    }
+                    embedded_hal_async::i2c::Operation::Write(buf) => {
+                        self.write_internal_async(buf, last).await?
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
 impl<T: Deref<Target = Block>, PINS> Read for I2C<T, PINS, Controller> {
     type Error = Error;
 
@@ -5,6 +5,10 @@
 
 #![warn(missing_docs)]
 #![no_std]
+#![cfg_attr(
+    feature = "eh1_0_async",
+    feature(generic_associated_types, type_alias_impl_trait)
+)]
 
 extern crate cortex_m;
 extern crate embedded_hal as hal;
@@ -17,18 +21,23 @@ pub extern crate rp2040_pac as pac;
 mod intrinsics;
 
 pub mod adc;
+#[cfg(feature = "async")]
+mod async_utils;
 pub(crate) mod atomic_register_access;
 pub mod clocks;
 mod critical_section_impl;
 pub mod dma;
+pub mod flash;
 mod float;
 pub mod gpio;
 pub mod i2c;
 pub mod multicore;
 pub mod pio;
 pub mod pll;
+pub mod power;
 pub mod prelude;
 pub mod pwm;
+pub(crate) mod ram_register_access;
 pub mod resets;
 pub mod rom_data;
 pub mod rosc;
@@ -36,10 +45,12 @@ pub mod rtc;
 pub mod sio;
 pub mod spi;
 pub mod ssi;
+pub mod sysinfo;
 pub mod timer;
 pub mod typelevel;
 pub mod uart;
 pub mod usb;
+pub mod vreg;
 pub mod watchdog;
 pub mod xosc;
 
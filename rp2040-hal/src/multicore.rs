@@ -32,13 +32,45 @@
 //!
 //! For inter-processor communications, see [`crate::sio::SioFifo`] and [`crate::sio::Spinlock0`]
 //!
+//! # Lockout
+//!
+//! Some operations, like erasing or programming the flash, require that the other core is not
+//! executing code from flash. The lockout functions pause the other core in a RAM-placed loop
+//! until it is released, using the inter-core FIFO:
+//!
+//! ```no_run
+//! use rp2040_hal::{multicore, sio::SioFifo};
+//!
+//! // On the victim core, during initialization:
+//! // Safety: No critical sections are in progress.
+//! unsafe { multicore::lockout_victim_init() };
+//!
+//! // In the SIO_IRQ_PROC0 or SIO_IRQ_PROC1 interrupt handler on the victim core:
+//! fn on_fifo_interrupt(fifo: &mut SioFifo) {
+//!     multicore::lockout_victim_handler(fifo);
+//! }
+//!
+//! // On the other core:
+//! fn save_settings(fifo: &mut SioFifo) {
+//!     multicore::lockout_start_blocking(fifo);
+//!     // The victim core is parked, and may not be executing from flash.
+//!     multicore::lockout_end_blocking(fifo);
+//! }
+//! ```
+//!
+//! The FIFO interrupt handler on the victim core must be the only user of the FIFO on that core,
+//! and any other data received by the initiator while waiting for the victim is discarded.
+//!
 //! For a detailed example, see [examples/multicore_fifo_blink.rs](https://github.com/rp-rs/rp-hal/tree/main/rp2040-hal/examples/multicore_fifo_blink.rs)
 
 use core::mem::ManuallyDrop;
+use core::ptr::write_volatile;
 use core::sync::atomic::compiler_fence;
 use core::sync::atomic::Ordering;
 
 use crate::pac;
+use crate::ram_register_access as ram;
+use crate::sio::SioFifo;
 use crate::Sio;
 
 /// Errors for multicore operations.
@@ -255,6 +287,102 @@ impl<'p> Core<'p> {
     }
 }
 
+/// Sent to the victim core to request a lockout, and echoed back once it is parked
+const LOCKOUT_MAGIC_START: u32 = 0x73a8_831e;
+/// Sent to the victim core to end a lockout, and echoed back once it has resumed
+const LOCKOUT_MAGIC_END: u32 = !LOCKOUT_MAGIC_START;
+
+/// Address of the SIO FIFO_ST register
+const SIO_FIFO_ST: *mut u32 = 0xd000_0050 as _;
+/// Address of the SIO FIFO_WR register
+const SIO_FIFO_WR: *mut u32 = 0xd000_0054 as _;
+/// Address of the SIO FIFO_RD register
+const SIO_FIFO_RD: *const u32 = 0xd000_0058 as _;
+/// FIFO_ST.VLD: the receive FIFO contains data
+const FIFO_ST_VLD: u32 = 1 << 0;
+/// FIFO_ST.RDY: the transmit FIFO has space
+const FIFO_ST_RDY: u32 = 1 << 1;
+/// FIFO_ST.WOF and FIFO_ST.ROE: the sticky error flags, cleared by writing to them
+const FIFO_ST_ERRORS: u32 = (1 << 2) | (1 << 3);
+
+/// Prepare the current core to be locked out by the other core.
+///
+/// This unmasks the FIFO interrupt of the current core (`SIO_IRQ_PROC0` or `SIO_IRQ_PROC1`),
+/// whose handler has to call [`lockout_victim_handler`].
+///
+/// # Safety
+///
+/// Unmasking an interrupt can break mask-based critical sections.
+pub unsafe fn lockout_victim_init() {
+    // Drop any stale data, so that the interrupt does not fire right away.
+    let peripherals = pac::Peripherals::steal();
+    let mut sio = Sio::new(peripherals.SIO);
+    sio.fifo.drain();
+    write_volatile(SIO_FIFO_ST, FIFO_ST_ERRORS);
+
+    match Sio::core() {
+        0 => pac::NVIC::unmask(pac::Interrupt::SIO_IRQ_PROC0),
+        _ => pac::NVIC::unmask(pac::Interrupt::SIO_IRQ_PROC1),
+    }
+}
+
+/// Handle a lockout request from the other core.
+///
+/// This has to be called from the FIFO interrupt handler of the victim core. If a lockout was
+/// requested, the core is parked in RAM with interrupts disabled until it is released by
+/// [`lockout_end_blocking`]. Any other data received over the FIFO is discarded.
+pub fn lockout_victim_handler(fifo: &mut SioFifo) {
+    while let Some(word) = fifo.read() {
+        if word == LOCKOUT_MAGIC_START {
+            // Safety: The parking loop only accesses the FIFO, which we own.
+            cortex_m::interrupt::free(|_| unsafe { park_in_ram() });
+        }
+    }
+    // Clear the error flags, which would keep the interrupt pending.
+    // Safety: This only affects the FIFO, which we own.
+    unsafe { write_volatile(SIO_FIFO_ST, FIFO_ST_ERRORS) };
+}
+
+/// Acknowledge the lockout and wait for it to end, without touching the flash.
+///
+/// The acknowledgement is sent from here, so that the initiator can not start a flash operation
+/// while this core is still executing from flash. This function is placed in RAM and must not
+/// call anything located in flash, so it only uses the always inlined register accesses of
+/// [`ram_register_access`](crate::ram_register_access), also in unoptimized builds.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn park_in_ram() {
+    while ram::read_u32(SIO_FIFO_ST) & FIFO_ST_RDY == 0 {}
+    ram::write_u32(SIO_FIFO_WR, LOCKOUT_MAGIC_START);
+    loop {
+        while ram::read_u32(SIO_FIFO_ST) & FIFO_ST_VLD == 0 {}
+        if ram::read_u32(SIO_FIFO_RD) == LOCKOUT_MAGIC_END {
+            break;
+        }
+    }
+    while ram::read_u32(SIO_FIFO_ST) & FIFO_ST_RDY == 0 {}
+    ram::write_u32(SIO_FIFO_WR, LOCKOUT_MAGIC_END);
+}
+
+/// Lock out the other core, blocking until it is parked in RAM.
+///
+/// The other core must have been prepared using [`lockout_victim_init`] and be handling its FIFO
+/// interrupt using [`lockout_victim_handler`], otherwise this never returns.
+pub fn lockout_start_blocking(fifo: &mut SioFifo) {
+    lockout_exchange(fifo, LOCKOUT_MAGIC_START);
+}
+
+/// Release the other core after [`lockout_start_blocking`], blocking until it has resumed.
+pub fn lockout_end_blocking(fifo: &mut SioFifo) {
+    lockout_exchange(fifo, LOCKOUT_MAGIC_END);
+}
+
+/// Send `magic` to the other core and wait until it is echoed back.
+fn lockout_exchange(fifo: &mut SioFifo, magic: u32) {
+    fifo.write_blocking(magic);
+    while fifo.read_blocking() != magic {}
+}
+
 // https://github.com/nvzqz/bad-rs/blob/master/src/never.rs
 mod bad {
     pub(crate) type Never = <F as HasOutput>::Output;
@@ -1,7 +1,28 @@
 //! Programmable IO (PIO)
 //! See [Chapter 3 of the datasheet](https://rptl.io/rp2040-datasheet#section_pio) for more details.
+//!
+//! ## DMA
+//!
+//! [`Rx`] and [`Tx`] can be used as endless source and sink of DMA transfers, so that the FIFOs
+//! are served without involving the CPU. They work with all transfer types in
+//! [`dma`](crate::dma): [`single_buffer`](crate::dma::single_buffer) to send or receive one buffer,
+//! [`double_buffer`](crate::dma::double_buffer) to stream without gaps, and
+//! [`ring`](crate::dma::ring) to endlessly repeat a buffer or to continuously capture into one.
+//!
+//! ```no_run
+//! use rp2040_hal::{dma::{single_buffer, Channel, CH0}, pio::{Tx, PIO0SM0}};
+//!
+//! fn show(ch: Channel<CH0>, tx: Tx<PIO0SM0>) -> (Channel<CH0>, Tx<PIO0SM0>) {
+//!     let pixels = cortex_m::singleton!(: [u32; 64] = [0; 64]).unwrap();
+//!     // Feed the state machine without the CPU having to wait for space in the FIFO.
+//!     let transfer = single_buffer::Config::new(ch, pixels, tx).start();
+//!     let (ch, _pixels, tx) = transfer.wait();
+//!     (ch, tx)
+//! }
+//! ```
 use crate::{
     atomic_register_access::{write_bitmask_clear, write_bitmask_set},
+    dma::{EndlessReadTarget, EndlessWriteTarget, ReadTarget, WriteTarget},
     resets::SubsystemReset,
 };
 use pio::{Program, SideSet, Wrap};
@@ -1149,6 +1170,26 @@ impl<SM: ValidStateMachine> Rx<SM> {
     }
 }
 
+// Safety: The RX FIFO register stays valid for as long as `Rx` exists and the address never
+// changes.
+unsafe impl<SM: ValidStateMachine> ReadTarget for Rx<SM> {
+    type ReceivedWord = u32;
+
+    fn rx_treq(&self) -> Option<u8> {
+        Some(self.dreq_value())
+    }
+
+    fn rx_address_count(&self) -> (u32, u32) {
+        (self.fifo_address() as u32, u32::MAX)
+    }
+
+    fn rx_increment(&self) -> bool {
+        false
+    }
+}
+
+impl<SM: ValidStateMachine> EndlessReadTarget for Rx<SM> {}
+
 /// PIO TX FIFO handle.
 pub struct Tx<SM: ValidStateMachine> {
     block: *const rp2040_pac::pio0::RegisterBlock,
@@ -1341,6 +1382,26 @@ impl<SM: ValidStateMachine> Tx<SM> {
     }
 }
 
+// Safety: The TX FIFO register stays valid for as long as `Tx` exists and the address never
+// changes.
+unsafe impl<SM: ValidStateMachine> WriteTarget for Tx<SM> {
+    type TransmittedWord = u32;
+
+    fn tx_treq(&self) -> Option<u8> {
+        Some(self.dreq_value())
+    }
+
+    fn tx_address_count(&mut self) -> (u32, u32) {
+        (self.fifo_address() as u32, u32::MAX)
+    }
+
+    fn tx_increment(&self) -> bool {
+        false
+    }
+}
+
+impl<SM: ValidStateMachine> EndlessWriteTarget for Tx<SM> {}
+
 /// PIO Interrupt controller.
 #[derive(Debug)]
 pub struct Interrupt<P: PIOExt> {
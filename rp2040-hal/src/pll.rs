@@ -295,14 +295,24 @@ pub fn setup_pll_blocking<D: PhaseLockedLoopDevice, R: Rate>(
 where
     R: Into<Hertz<u64>>,
 {
+    let pll = PhaseLockedLoop::new(dev, xosc_frequency, config)?;
+    Ok(start_pll_blocking(pll, clocks, resets))
+}
+
+/// Start a PLL whose configuration was already validated, and wait for it to lock.
+pub(crate) fn start_pll_blocking<D: PhaseLockedLoopDevice>(
+    pll: PhaseLockedLoop<Disabled, D>,
+    clocks: &mut ClocksManager,
+    resets: &mut RESETS,
+) -> PhaseLockedLoop<Locked, D> {
     // Before we touch PLLs, switch sys and ref cleanly away from their aux sources.
     nb::block!(clocks.system_clock.reset_source_await()).unwrap();
 
     nb::block!(clocks.reference_clock.reset_source_await()).unwrap();
 
-    let initialized_pll = PhaseLockedLoop::new(dev, xosc_frequency, config)?.initialize(resets);
+    let initialized_pll = pll.initialize(resets);
 
     let locked_pll_token = nb::block!(initialized_pll.await_lock()).unwrap();
 
-    Ok(initialized_pll.get_locked(locked_pll_token))
+    initialized_pll.get_locked(locked_pll_token)
 }
@@ -0,0 +1,397 @@
+//! Low power modes
+//!
+//! Besides stopping the processor with `wfi`, the RP2040 has two low power states:
+//!
+//! - **SLEEP**: the processors are stopped and only the clocks enabled in `SLEEP_EN0`/`SLEEP_EN1`
+//!   keep running. [`PowerManager::sleep_until_alarm`] only keeps the RTC running and wakes up
+//!   when its alarm matches.
+//! - **DORMANT**: the crystal and ring oscillators are stopped, so no clock is running at all.
+//!   [`PowerManager::dormant_until_pin`] wakes up on an edge or level of a GPIO pin.
+//!
+//! Before entering either state `clk_ref` and `clk_sys` are switched to the crystal oscillator and
+//! both PLLs are powered down. After waking up the PLLs are started again and every clock is
+//! restored to the configuration it had before, so the frequencies known to the
+//! [`ClocksManager`](crate::clocks::ClocksManager) stay valid. `clk_peri`, `clk_usb` and `clk_adc`
+//! are stopped while sleeping, the GPIO output clocks are left alone. The ring oscillator keeps
+//! running in SLEEP, and is restarted after DORMANT if it was running before.
+//!
+//! The crystal oscillator has to be running, e.g. after
+//! [`init_clocks_and_plls`](crate::clocks::init_clocks_and_plls):
+//!
+//! ```ignore
+//! use embedded_time::rate::Extensions;
+//! use rp2040_hal::{gpio::Interrupt, power::PowerManager, rtc::DateTimeFilter};
+//!
+//! let mut power = PowerManager::new(XOSC_CRYSTAL_FREQ.Hz());
+//!
+//! // Wake up at the start of the next minute
+//! power
+//!     .sleep_until_alarm(
+//!         &mut clocks.system_clock,
+//!         &mut core.SCB,
+//!         &mut rtc,
+//!         DateTimeFilter::default().second(0),
+//!     )
+//!     .unwrap();
+//!
+//! // Wake up when the button is pressed
+//! power
+//!     .dormant_until_pin(&mut clocks.system_clock, &mut button, Interrupt::EdgeLow)
+//!     .unwrap();
+//! ```
+//!
+//! Both functions run with interrupts disabled on the calling core. The other core must not
+//! change the clock configuration at the same time.
+
+use crate::clocks::{fractional_div, ClockGate, ClockGates, SystemClock};
+use crate::gpio::bank0::BankPinId;
+use crate::gpio::{Interrupt, Pin, PinId, PinMode, ValidPinMode};
+use crate::rtc::{DateTimeFilter, RealTimeClock};
+use cortex_m::peripheral::SCB;
+use embedded_time::fixed_point::FixedPoint;
+use embedded_time::rate::Hertz;
+use pac::{clocks, pll_sys};
+
+/// Errors which can occur when entering a low power mode
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The crystal oscillator is not running
+    XoscNotRunning,
+    /// `clk_rtc` can not be derived from the crystal oscillator at its current frequency
+    RtcFrequencyTooHigh,
+}
+
+/// Puts the chip into the SLEEP and DORMANT states
+pub struct PowerManager {
+    xosc_frequency: Hertz,
+}
+
+impl PowerManager {
+    /// Create a new power manager. `xosc_crystal_freq` is the frequency of the crystal
+    /// oscillator, which is used to clock the chip while sleeping.
+    pub fn new(xosc_crystal_freq: Hertz) -> Self {
+        Self {
+            xosc_frequency: xosc_crystal_freq,
+        }
+    }
+
+    /// Enter SLEEP until the RTC alarm matches `filter`.
+    ///
+    /// Only `clk_rtc` is kept running, from the crystal oscillator at the frequency the RTC was
+    /// configured for. The alarm and the RTC interrupt are disabled again before returning, so
+    /// no `RTC_IRQ` handler runs.
+    ///
+    /// `system_clock` is borrowed to make sure the clock configuration can not be changed while
+    /// it is temporarily replaced.
+    pub fn sleep_until_alarm(
+        &mut self,
+        _system_clock: &mut SystemClock,
+        scb: &mut SCB,
+        rtc: &mut RealTimeClock,
+        filter: DateTimeFilter,
+    ) -> Result<(), Error> {
+        let rtc_div = fractional_div(self.xosc_frequency.integer(), rtc.clock_frequency())
+            .filter(|&div| div >= 1 << 8)
+            .ok_or(Error::RtcFrequencyTooHigh)?;
+
+        cortex_m::interrupt::free(|_| {
+            let state = self.run_from_xosc(Some(rtc_div))?;
+
+            // Safety: The clocks are owned by the borrowed `SystemClock`.
+            let clocks = unsafe { &*pac::CLOCKS::ptr() };
+            let sleep_enabled = ClockGates::read_sleep(clocks);
+            ClockGates::none()
+                .enable(ClockGate::RtcRtc)
+                .write_sleep(clocks);
+
+            rtc.schedule_alarm(filter);
+            rtc.enable_interrupt();
+
+            // With SEVONPEND the pending RTC interrupt wakes up `wfe`, even though interrupts
+            // are disabled and it may be masked in the NVIC.
+            scb.set_sleepdeep();
+            scb.set_sevonpend();
+            while !rtc.is_interrupt_pending() {
+                cortex_m::asm::wfe();
+            }
+            scb.clear_sevonpend();
+            scb.clear_sleepdeep();
+
+            rtc.disable_interrupt();
+            rtc.clear_interrupt();
+            pac::NVIC::unpend(pac::Interrupt::RTC_IRQ);
+
+            sleep_enabled.write_sleep(clocks);
+
+            self.restore(&state, true);
+            Ok(())
+        })
+    }
+
+    /// Enter DORMANT until `pin` sees `wake`.
+    ///
+    /// Edges which happened before this function is called are discarded.
+    ///
+    /// `system_clock` is borrowed to make sure the clock configuration can not be changed while
+    /// it is temporarily replaced.
+    pub fn dormant_until_pin<I, M>(
+        &mut self,
+        _system_clock: &mut SystemClock,
+        pin: &mut Pin<I, M>,
+        wake: Interrupt,
+    ) -> Result<(), Error>
+    where
+        I: PinId + BankPinId,
+        M: PinMode + ValidPinMode<I>,
+    {
+        cortex_m::interrupt::free(|_| {
+            let state = self.run_from_xosc(None)?;
+
+            pin.clear_interrupt(wake);
+            pin.set_dormant_wake_enabled(wake, true);
+
+            // Safety: The crystal oscillator is running, and all clocks are derived from it.
+            let (xosc, rosc) = unsafe { (&*pac::XOSC::ptr(), &*pac::ROSC::ptr()) };
+
+            // Stop the ring oscillator as well, nothing is clocked from it anymore.
+            let rosc_enabled = rosc.status.read().enabled().bit_is_set();
+            if rosc_enabled {
+                rosc.ctrl.modify(|_, w| w.enable().disable());
+            }
+
+            // Taken from the C SDK
+            const XOSC_DORMANT_VALUE: u32 = 0x636f6d61;
+            xosc.dormant
+                .write(|w| unsafe { w.bits(XOSC_DORMANT_VALUE) });
+            while xosc.status.read().stable().bit_is_clear() {
+                core::hint::spin_loop();
+            }
+
+            if rosc_enabled {
+                rosc.ctrl.modify(|_, w| w.enable().enable());
+                while rosc.status.read().stable().bit_is_clear() {
+                    core::hint::spin_loop();
+                }
+            }
+
+            pin.set_dormant_wake_enabled(wake, false);
+            pin.clear_interrupt(wake);
+
+            self.restore(&state, false);
+            Ok(())
+        })
+    }
+
+    /// Switch `clk_ref` and `clk_sys` to the crystal oscillator, stop the other clocks and power
+    /// down the PLLs. With `rtc_div`, `clk_rtc` keeps running from the crystal oscillator.
+    fn run_from_xosc(&self, rtc_div: Option<u32>) -> Result<ClockState, Error> {
+        // Safety: Only called with the borrowed `SystemClock`, and within a critical section.
+        let (clocks, xosc) = unsafe { (&*pac::CLOCKS::ptr(), &*pac::XOSC::ptr()) };
+        if xosc.status.read().stable().bit_is_clear() {
+            return Err(Error::XoscNotRunning);
+        }
+
+        let state = ClockState::save(clocks);
+
+        // Move clk_sys away from any aux source first, then clk_ref can be changed.
+        clocks.clk_sys_ctrl.modify(|_, w| w.src().clk_ref());
+        while clocks.clk_sys_selected.read().bits() != 1 {
+            core::hint::spin_loop();
+        }
+        clocks.clk_ref_ctrl.modify(|_, w| w.src().xosc_clksrc());
+        while clocks.clk_ref_selected.read().bits() != 1 << 2 {
+            core::hint::spin_loop();
+        }
+        clocks.clk_ref_div.write(|w| unsafe { w.bits(1 << 8) });
+        clocks.clk_sys_div.write(|w| unsafe { w.bits(1 << 8) });
+
+        clocks.clk_peri_ctrl.modify(|_, w| w.enable().clear_bit());
+        clocks.clk_usb_ctrl.modify(|_, w| w.enable().clear_bit());
+        clocks.clk_adc_ctrl.modify(|_, w| w.enable().clear_bit());
+        clocks.clk_rtc_ctrl.modify(|_, w| w.enable().clear_bit());
+        if let Some(div) = rtc_div {
+            self.await_rtc_clock_stopped();
+            clocks.clk_rtc_ctrl.modify(|_, w| w.auxsrc().xosc_clksrc());
+            clocks.clk_rtc_div.write(|w| unsafe { w.bits(div) });
+            clocks.clk_rtc_ctrl.modify(|_, w| w.enable().set_bit());
+        }
+
+        // Safety: No clock is derived from the PLLs anymore.
+        unsafe {
+            (*pac::PLL_SYS::ptr()).pwr.reset();
+            (*pac::PLL_USB::ptr()).pwr.reset();
+        }
+
+        Ok(state)
+    }
+
+    /// Restart the PLLs and restore the clocks saved by [`run_from_xosc`](Self::run_from_xosc).
+    /// `rtc_running` tells if `clk_rtc` was kept running from the crystal oscillator.
+    fn restore(&self, state: &ClockState, rtc_running: bool) {
+        // Safety: Only called with the borrowed `SystemClock`, and within a critical section.
+        let clocks = unsafe { &*pac::CLOCKS::ptr() };
+        unsafe {
+            state.pll_sys.restore(&*pac::PLL_SYS::ptr());
+            state.pll_usb.restore(&*pac::PLL_USB::ptr());
+        }
+
+        if rtc_running {
+            clocks.clk_rtc_ctrl.modify(|_, w| w.enable().clear_bit());
+            self.await_rtc_clock_stopped();
+        }
+        clocks
+            .clk_rtc_ctrl
+            .write(|w| unsafe { w.bits(state.rtc_ctrl & !CTRL_ENABLE) });
+        clocks
+            .clk_rtc_div
+            .write(|w| unsafe { w.bits(state.rtc_div) });
+        clocks
+            .clk_rtc_ctrl
+            .write(|w| unsafe { w.bits(state.rtc_ctrl) });
+
+        clocks
+            .clk_adc_div
+            .write(|w| unsafe { w.bits(state.adc_div) });
+        clocks
+            .clk_adc_ctrl
+            .write(|w| unsafe { w.bits(state.adc_ctrl) });
+        clocks
+            .clk_usb_div
+            .write(|w| unsafe { w.bits(state.usb_div) });
+        clocks
+            .clk_usb_ctrl
+            .write(|w| unsafe { w.bits(state.usb_ctrl) });
+        clocks
+            .clk_peri_ctrl
+            .write(|w| unsafe { w.bits(state.peri_ctrl) });
+
+        // The glitchless muxes still select the crystal oscillator, so the aux muxes can be
+        // changed before switching over.
+        clocks
+            .clk_ref_ctrl
+            .write(|w| unsafe { w.bits((state.ref_ctrl & !REF_CTRL_SRC) | 2) });
+        clocks
+            .clk_ref_div
+            .write(|w| unsafe { w.bits(state.ref_div) });
+        clocks
+            .clk_ref_ctrl
+            .write(|w| unsafe { w.bits(state.ref_ctrl) });
+        while clocks.clk_ref_selected.read().bits() != 1 << (state.ref_ctrl & REF_CTRL_SRC) {
+            core::hint::spin_loop();
+        }
+
+        clocks
+            .clk_sys_ctrl
+            .write(|w| unsafe { w.bits(state.sys_ctrl & !SYS_CTRL_SRC) });
+        clocks
+            .clk_sys_div
+            .write(|w| unsafe { w.bits(state.sys_div) });
+        clocks
+            .clk_sys_ctrl
+            .write(|w| unsafe { w.bits(state.sys_ctrl) });
+        while clocks.clk_sys_selected.read().bits() != 1 << (state.sys_ctrl & SYS_CTRL_SRC) {
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Wait until disabling `clk_rtc` propagated, while `clk_sys` runs from the crystal
+    /// oscillator. `clk_rtc` runs at the frequency the RTC was configured for, both before and
+    /// after switching its source.
+    fn await_rtc_clock_stopped(&self) {
+        // Safety: Read only access to the RTC divider.
+        let rtc_frequency = unsafe { (*pac::RTC::ptr()).clkdiv_m1.read().bits() } + 1;
+        // Delay for 3 cycles of the target clock, for ENABLE propagation.
+        cortex_m::asm::delay(self.xosc_frequency.integer() / rtc_frequency * 3 + 1);
+    }
+}
+
+/// `ENABLE` bit of the aux clock `CTRL` registers
+const CTRL_ENABLE: u32 = 1 << 11;
+/// `SRC` field of `CLK_REF_CTRL`
+const REF_CTRL_SRC: u32 = 0x3;
+/// `SRC` field of `CLK_SYS_CTRL`
+const SYS_CTRL_SRC: u32 = 0x1;
+
+/// Clock configuration saved while running from the crystal oscillator
+struct ClockState {
+    ref_ctrl: u32,
+    ref_div: u32,
+    sys_ctrl: u32,
+    sys_div: u32,
+    peri_ctrl: u32,
+    usb_ctrl: u32,
+    usb_div: u32,
+    adc_ctrl: u32,
+    adc_div: u32,
+    rtc_ctrl: u32,
+    rtc_div: u32,
+    pll_sys: PllState,
+    pll_usb: PllState,
+}
+
+impl ClockState {
+    fn save(clocks: &clocks::RegisterBlock) -> Self {
+        // Safety: Read only access to the PLL registers.
+        let (pll_sys, pll_usb) = unsafe {
+            (
+                PllState::save(&*pac::PLL_SYS::ptr()),
+                PllState::save(&*pac::PLL_USB::ptr()),
+            )
+        };
+        Self {
+            ref_ctrl: clocks.clk_ref_ctrl.read().bits(),
+            ref_div: clocks.clk_ref_div.read().bits(),
+            sys_ctrl: clocks.clk_sys_ctrl.read().bits(),
+            sys_div: clocks.clk_sys_div.read().bits(),
+            peri_ctrl: clocks.clk_peri_ctrl.read().bits(),
+            usb_ctrl: clocks.clk_usb_ctrl.read().bits(),
+            usb_div: clocks.clk_usb_div.read().bits(),
+            adc_ctrl: clocks.clk_adc_ctrl.read().bits(),
+            adc_div: clocks.clk_adc_div.read().bits(),
+            rtc_ctrl: clocks.clk_rtc_ctrl.read().bits(),
+            rtc_div: clocks.clk_rtc_div.read().bits(),
+            pll_sys,
+            pll_usb,
+        }
+    }
+}
+
+/// PLL configuration saved while the PLL is powered down
+struct PllState {
+    cs: u32,
+    pwr: u32,
+    fbdiv_int: u32,
+    prim: u32,
+}
+
+impl PllState {
+    fn save(pll: &pll_sys::RegisterBlock) -> Self {
+        Self {
+            cs: pll.cs.read().bits(),
+            pwr: pll.pwr.read().bits(),
+            fbdiv_int: pll.fbdiv_int.read().bits(),
+            prim: pll.prim.read().bits(),
+        }
+    }
+
+    /// Start the PLL again, if it was running when saved.
+    fn restore(&self, pll: &pll_sys::RegisterBlock) {
+        // PD bit of the PWR register
+        if self.pwr & 1 != 0 {
+            return;
+        }
+        pll.cs.write(|w| unsafe { w.bits(self.cs) });
+        pll.fbdiv_int.write(|w| unsafe { w.bits(self.fbdiv_int) });
+        pll.pwr.modify(|_, w| {
+            w.pd().clear_bit();
+            w.vcopd().clear_bit();
+            w
+        });
+        while pll.cs.read().lock().bit_is_clear() {
+            core::hint::spin_loop();
+        }
+        pll.prim.write(|w| unsafe { w.bits(self.prim) });
+        pll.pwr.write(|w| unsafe { w.bits(self.pwr) });
+    }
+}
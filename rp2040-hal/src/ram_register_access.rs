@@ -0,0 +1,85 @@
+//! Register access for functions running from RAM
+//!
+//! Functions which run while the flash is not accessible, e.g. because XIP is disabled, must not
+//! call anything located in flash. The functions of `core::ptr` are not inlined in unoptimized
+//! builds, so these helpers use inline assembly instead, which is always inlined.
+
+/// Volatile 32 bit read from `address`, which is always inlined.
+///
+/// # Safety
+///
+/// Same as [core::ptr::read_volatile].
+#[inline(always)]
+pub(crate) unsafe fn read_u32(address: *const u32) -> u32 {
+    #[cfg(target_arch = "arm")]
+    {
+        let value: u32;
+        core::arch::asm!(
+            "ldr {value}, [{address}]",
+            address = in(reg) address,
+            value = out(reg) value,
+            options(nostack, preserves_flags, readonly),
+        );
+        value
+    }
+    #[cfg(not(target_arch = "arm"))]
+    core::ptr::read_volatile(address)
+}
+
+/// Volatile 32 bit write of `value` to `address`, which is always inlined.
+///
+/// # Safety
+///
+/// Same as [core::ptr::write_volatile].
+#[inline(always)]
+pub(crate) unsafe fn write_u32(address: *mut u32, value: u32) {
+    #[cfg(target_arch = "arm")]
+    core::arch::asm!(
+        "str {value}, [{address}]",
+        address = in(reg) address,
+        value = in(reg) value,
+        options(nostack, preserves_flags),
+    );
+    #[cfg(not(target_arch = "arm"))]
+    core::ptr::write_volatile(address, value)
+}
+
+/// Volatile 8 bit read from `address`, which is always inlined.
+///
+/// # Safety
+///
+/// Same as [core::ptr::read_volatile].
+#[inline(always)]
+pub(crate) unsafe fn read_u8(address: *const u8) -> u8 {
+    #[cfg(target_arch = "arm")]
+    {
+        let value: u32;
+        core::arch::asm!(
+            "ldrb {value}, [{address}]",
+            address = in(reg) address,
+            value = out(reg) value,
+            options(nostack, preserves_flags, readonly),
+        );
+        value as u8
+    }
+    #[cfg(not(target_arch = "arm"))]
+    core::ptr::read_volatile(address)
+}
+
+/// Volatile 8 bit write of `value` to `address`, which is always inlined.
+///
+/// # Safety
+///
+/// Same as [core::ptr::write_volatile].
+#[inline(always)]
+pub(crate) unsafe fn write_u8(address: *mut u8, value: u8) {
+    #[cfg(target_arch = "arm")]
+    core::arch::asm!(
+        "strb {value}, [{address}]",
+        address = in(reg) address,
+        value = in(reg) value as u32,
+        options(nostack, preserves_flags),
+    );
+    #[cfg(not(target_arch = "arm"))]
+    core::ptr::write_volatile(address, value)
+}
@@ -194,6 +194,26 @@ impl RealTimeClock {
     pub fn clear_interrupt(&mut self) {
         self.disable_alarm();
     }
+
+    /// Enable the `RTC_IRQ` interrupt, which is raised when the scheduled alarm matches.
+    pub fn enable_interrupt(&mut self) {
+        self.rtc.inte.modify(|_, w| w.rtc().set_bit());
+    }
+
+    /// Disable the `RTC_IRQ` interrupt.
+    pub fn disable_interrupt(&mut self) {
+        self.rtc.inte.modify(|_, w| w.rtc().clear_bit());
+    }
+
+    /// Check if the alarm matched and the interrupt is enabled.
+    pub fn is_interrupt_pending(&self) -> bool {
+        self.rtc.ints.read().rtc().bit_is_set()
+    }
+
+    /// Frequency of `clk_rtc` the RTC was configured for, in Hz.
+    pub(crate) fn clock_frequency(&self) -> u32 {
+        self.rtc.clkdiv_m1.read().bits() + 1
+    }
 }
 
 /// Errors that can occur on methods on [RtcClock]
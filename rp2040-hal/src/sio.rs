@@ -17,6 +17,39 @@
 //! # let sio = Sio::new(peripherals.SIO);
 //! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
 //! ```
+//!
+//! The interpolators can be used for fast fixed-point arithmetic and table lookups, e.g. to step
+//! through a table of 16 bit values:
+//!
+//! ```no_run
+//! use rp2040_hal::{pac, sio::{Interp, Lane, LaneCtrl, Sio}};
+//!
+//! static TABLE: [u16; 256] = [0; 256];
+//!
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let mut sio = Sio::new(peripherals.SIO);
+//! let interp = &mut sio.interp0;
+//! // Lane 0 masks the byte offset in its accumulator, and adds it to the table address. On every
+//! // pop, its accumulator is replaced by the result of lane 1.
+//! interp.lane0.set_ctrl(LaneCtrl {
+//!     mask_lsb: 1,
+//!     mask_msb: 8,
+//!     cross_result: true,
+//!     ..LaneCtrl::new()
+//! });
+//! interp.lane0.set_base(TABLE.as_ptr() as u32);
+//! // Lane 1 adds 2 to the accumulator of lane 0, i.e. steps to the next element.
+//! interp.lane1.set_ctrl(LaneCtrl {
+//!     cross_input: true,
+//!     ..LaneCtrl::new()
+//! });
+//! interp.lane0.set_accum(0);
+//! interp.lane1.set_base(2);
+//! let first = unsafe { *(interp.lane0.pop() as *const u16) };
+//! ```
+//!
+//! The interpolators are part of the SIO block of each core, so each core can only use the
+//! interpolators of the `Sio` it created.
 
 use super::*;
 use core::convert::Infallible;
@@ -60,9 +93,10 @@ pub struct Sio {
     pub hwdivider: HwDivider,
     /// Inter-core FIFO
     pub fifo: SioFifo,
-    // we can hand out other things here, for example:
-    // interp0
-    // interp1
+    /// Interpolator 0
+    pub interp0: Interp0,
+    /// Interpolator 1
+    pub interp1: Interp1,
 }
 
 impl Sio {
@@ -74,6 +108,14 @@ impl Sio {
             gpio_qspi: SioGpioQspi { _private: () },
             fifo: SioFifo { _private: () },
             hwdivider: HwDivider { _private: () },
+            interp0: Interp0 {
+                lane0: Interp0Lane0 { _private: () },
+                lane1: Interp0Lane1 { _private: () },
+            },
+            interp1: Interp1 {
+                lane0: Interp1Lane0 { _private: () },
+                lane1: Interp1Lane1 { _private: () },
+            },
         }
     }
 
@@ -667,3 +709,255 @@ pub unsafe fn spinlock_reset() {
         SPINLOCK0_PTR.wrapping_add(i).write_volatile(1);
     }
 }
+
+/// Configuration of an interpolator lane, see
+/// [Chapter 2 Section 3.1.6](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf).
+///
+/// Each lane shifts its accumulator right by `shift`, keeps the bits `mask_lsb..=mask_msb` and
+/// optionally sign-extends them. The result is then added to the lane's base.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LaneCtrl {
+    /// Right rotation applied to the accumulator, `0..=31`
+    pub shift: u8,
+    /// Least significant bit of the mask, `0..=31`
+    pub mask_lsb: u8,
+    /// Most significant bit of the mask, `mask_lsb..=31`
+    pub mask_msb: u8,
+    /// Sign-extend the masked result from `mask_msb` to 32 bits
+    pub signed: bool,
+    /// Use the other lane's accumulator as input
+    pub cross_input: bool,
+    /// Feed the other lane's result back into this lane's accumulator when popping
+    pub cross_result: bool,
+    /// Add the shifted and masked accumulator to the base without sign extension or masking
+    pub add_raw: bool,
+    /// Value ORed into bits 29:28 of the lane result, `0..=3`
+    pub force_msb: u8,
+    /// Blend mode, only supported on lane 0 of interpolator 0.
+    ///
+    /// The full result becomes a linear interpolation between base 0 and base 1, with the
+    /// fraction taken from the low 8 bits of lane 1's shifted and masked accumulator.
+    pub blend: bool,
+    /// Clamp mode, only supported on lane 0 of interpolator 1.
+    ///
+    /// The lane result is clamped to `base0..=base1`.
+    pub clamp: bool,
+}
+
+impl LaneCtrl {
+    /// Configuration passing the accumulator through unchanged.
+    pub const fn new() -> Self {
+        Self {
+            shift: 0,
+            mask_lsb: 0,
+            mask_msb: 31,
+            signed: false,
+            cross_input: false,
+            cross_result: false,
+            add_raw: false,
+            force_msb: 0,
+            blend: false,
+            clamp: false,
+        }
+    }
+
+    /// Encode the configuration into the value of the CTRL_LANEx register.
+    pub const fn encode(&self) -> u32 {
+        (self.shift as u32 & 0x1f)
+            | ((self.mask_lsb as u32 & 0x1f) << 5)
+            | ((self.mask_msb as u32 & 0x1f) << 10)
+            | ((self.signed as u32) << 15)
+            | ((self.cross_input as u32) << 16)
+            | ((self.cross_result as u32) << 17)
+            | ((self.add_raw as u32) << 18)
+            | ((self.force_msb as u32 & 0x3) << 19)
+            | ((self.blend as u32) << 21)
+            | ((self.clamp as u32) << 22)
+    }
+}
+
+impl Default for LaneCtrl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A lane of an interpolator
+pub trait Lane {
+    /// Read the lane result, and update both accumulators as configured.
+    fn pop(&mut self) -> u32;
+    /// Read the lane result without updating the accumulators.
+    fn peek(&self) -> u32;
+    /// Set the accumulator.
+    fn set_accum(&mut self, value: u32);
+    /// Read the accumulator.
+    fn get_accum(&self) -> u32;
+    /// Set the base.
+    fn set_base(&mut self, value: u32);
+    /// Read the base.
+    fn get_base(&self) -> u32;
+    /// Configure the lane.
+    ///
+    /// Panics in debug builds if blend or clamp mode is requested on a lane not supporting it.
+    fn set_ctrl(&mut self, ctrl: LaneCtrl);
+    /// Atomically add `value` to the accumulator.
+    fn add_accum(&mut self, value: u32);
+    /// Read the shifted and masked accumulator, before it is added to the base.
+    fn read_raw(&self) -> u32;
+}
+
+/// An interpolator
+pub trait Interp {
+    /// Read the full result, i.e. the sum of both lane results and base 2, and update the
+    /// accumulators as configured.
+    fn pop(&mut self) -> u32;
+    /// Read the full result without updating the accumulators.
+    fn peek(&self) -> u32;
+    /// Set base 2, which is added to the full result.
+    fn set_base(&mut self, value: u32);
+    /// Read base 2.
+    fn get_base(&self) -> u32;
+    /// Set base 0 and base 1 at once, from the low and high 16 bits of `value`.
+    ///
+    /// The halves are sign-extended if the respective lane is configured as signed.
+    fn set_base_1and0(&mut self, value: u32);
+}
+
+macro_rules! interpolators {
+    ($($n:literal: [$($lane0_unsupported:ident),*]),+) => {
+        $crate::paste::paste! {
+            $(
+                #[doc = "Interpolator " $n ", owned by the core which created the [`Sio`]."]
+                ///
+                /// Each core has its own set of interpolators.
+                pub struct [<Interp $n>] {
+                    /// Lane 0
+                    pub lane0: [<Interp $n Lane0>],
+                    /// Lane 1
+                    pub lane1: [<Interp $n Lane1>],
+                }
+
+                impl Interp for [<Interp $n>] {
+                    fn pop(&mut self) -> u32 {
+                        let sio = unsafe { &(*pac::SIO::ptr()) };
+                        sio.[<interp $n _pop_full>].read().bits()
+                    }
+
+                    fn peek(&self) -> u32 {
+                        let sio = unsafe { &(*pac::SIO::ptr()) };
+                        sio.[<interp $n _peek_full>].read().bits()
+                    }
+
+                    fn set_base(&mut self, value: u32) {
+                        let sio = unsafe { &(*pac::SIO::ptr()) };
+                        sio.[<interp $n _base2>].write(|w| unsafe { w.bits(value) });
+                    }
+
+                    fn get_base(&self) -> u32 {
+                        let sio = unsafe { &(*pac::SIO::ptr()) };
+                        sio.[<interp $n _base2>].read().bits()
+                    }
+
+                    fn set_base_1and0(&mut self, value: u32) {
+                        let sio = unsafe { &(*pac::SIO::ptr()) };
+                        sio.[<interp $n _base_1and0>].write(|w| unsafe { w.bits(value) });
+                    }
+                }
+
+                interpolators!(@lane $n, 0, [$($lane0_unsupported),*]);
+                interpolators!(@lane $n, 1, [blend, clamp]);
+            )+
+        }
+    };
+    (@lane $n:literal, $lane:literal, [$($unsupported:ident),*]) => {
+        $crate::paste::paste! {
+            #[doc = "Lane " $lane " of interpolator " $n]
+            pub struct [<Interp $n Lane $lane>] {
+                _private: (),
+            }
+
+            impl Lane for [<Interp $n Lane $lane>] {
+                fn pop(&mut self) -> u32 {
+                    let sio = unsafe { &(*pac::SIO::ptr()) };
+                    sio.[<interp $n _pop_lane $lane>].read().bits()
+                }
+
+                fn peek(&self) -> u32 {
+                    let sio = unsafe { &(*pac::SIO::ptr()) };
+                    sio.[<interp $n _peek_lane $lane>].read().bits()
+                }
+
+                fn set_accum(&mut self, value: u32) {
+                    let sio = unsafe { &(*pac::SIO::ptr()) };
+                    sio.[<interp $n _accum $lane>].write(|w| unsafe { w.bits(value) });
+                }
+
+                fn get_accum(&self) -> u32 {
+                    let sio = unsafe { &(*pac::SIO::ptr()) };
+                    sio.[<interp $n _accum $lane>].read().bits()
+                }
+
+                fn set_base(&mut self, value: u32) {
+                    let sio = unsafe { &(*pac::SIO::ptr()) };
+                    sio.[<interp $n _base $lane>].write(|w| unsafe { w.bits(value) });
+                }
+
+                fn get_base(&self) -> u32 {
+                    let sio = unsafe { &(*pac::SIO::ptr()) };
+                    sio.[<interp $n _base $lane>].read().bits()
+                }
+
+                fn set_ctrl(&mut self, ctrl: LaneCtrl) {
+                    $(
+                        debug_assert!(
+                            !ctrl.$unsupported,
+                            concat!(stringify!($unsupported), " mode is not supported on this lane")
+                        );
+                    )*
+                    let sio = unsafe { &(*pac::SIO::ptr()) };
+                    sio.[<interp $n _ctrl_lane $lane>].write(|w| unsafe { w.bits(ctrl.encode()) });
+                }
+
+                fn add_accum(&mut self, value: u32) {
+                    let sio = unsafe { &(*pac::SIO::ptr()) };
+                    sio.[<interp $n _accum $lane _add>].write(|w| unsafe { w.bits(value) });
+                }
+
+                fn read_raw(&self) -> u32 {
+                    let sio = unsafe { &(*pac::SIO::ptr()) };
+                    sio.[<interp $n _accum $lane _add>].read().bits()
+                }
+            }
+        }
+    };
+}
+
+interpolators!(0: [clamp], 1: [blend]);
+
+#[cfg(test)]
+mod tests {
+    use super::LaneCtrl;
+
+    #[test]
+    fn test_lane_ctrl_encode() {
+        assert_eq!(LaneCtrl::new().encode(), 31 << 10);
+        let ctrl = |f: fn(&mut LaneCtrl)| {
+            let mut ctrl = LaneCtrl {
+                mask_msb: 0,
+                ..LaneCtrl::new()
+            };
+            f(&mut ctrl);
+            ctrl.encode()
+        };
+        assert_eq!(ctrl(|c| c.shift = 31), 0x1f);
+        assert_eq!(ctrl(|c| c.mask_lsb = 31), 0x1f << 5);
+        assert_eq!(ctrl(|c| c.mask_msb = 31), 0x1f << 10);
+        assert_eq!(ctrl(|c| c.signed = true), 1 << 15);
+        assert_eq!(ctrl(|c| c.cross_input = true), 1 << 16);
+        assert_eq!(ctrl(|c| c.cross_result = true), 1 << 17);
+        assert_eq!(ctrl(|c| c.add_raw = true), 1 << 18);
+        assert_eq!(ctrl(|c| c.force_msb = 3), 3 << 19);
+        assert_eq!(ctrl(|c| c.blend = true), 1 << 21);
+        assert_eq!(ctrl(|c| c.clamp = true), 1 << 22);
+    }
+}
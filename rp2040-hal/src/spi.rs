@@ -18,7 +18,37 @@
 //!
 //! let spi = Spi::<_, _, 8>::new(peripherals.SPI0).init(&mut peripherals.RESETS, 125_000_000u32.Hz(), 16_000_000u32.Hz(), &MODE_0);
 //! ```
+//!
+//! ## DMA
+//!
+//! Large buffers can be sent and received by the DMA unit instead of the CPU. The SPI driver is
+//! moved into the transfer and handed back once it has completed:
+//!
+//! ```no_run
+//! # use embedded_hal::spi::MODE_0;
+//! # use embedded_time::rate::*;
+//! # use rp2040_hal::{spi::Spi, gpio::{Pins, FunctionSpi}, pac, Sio};
+//! use rp2040_hal::dma::DMAExt;
+//!
+//! # let mut peripherals = pac::Peripherals::take().unwrap();
+//! # let spi = Spi::<_, _, 8>::new(peripherals.SPI0).init(&mut peripherals.RESETS, 125_000_000u32.Hz(), 16_000_000u32.Hz(), &MODE_0);
+//! let dma = peripherals.DMA.split(&mut peripherals.RESETS);
+//! let framebuffer = cortex_m::singleton!(: [u8; 1024] = [0; 1024]).unwrap();
+//!
+//! // Push the framebuffer out without the CPU having to feed the FIFO.
+//! let transfer = spi.write_dma(dma.ch0, framebuffer);
+//! let (ch0, framebuffer, spi) = transfer.wait();
+//!
+//! // Send and receive at the same time, using a pair of channels.
+//! let rx_buf = cortex_m::singleton!(: [u8; 1024] = [0; 1024]).unwrap();
+//! let transfer = spi.transfer_dma((ch0, dma.ch1), framebuffer, rx_buf);
+//! let ((ch0, ch1), framebuffer, spi, rx_buf) = transfer.wait();
+//! ```
 
+use crate::dma::{
+    bidirectional, single_buffer, EndlessReadTarget, EndlessWriteTarget, ReadTarget, SingleChannel,
+    WriteTarget,
+};
 use crate::resets::SubsystemReset;
 use core::{convert::Infallible, marker::PhantomData, ops::Deref};
 #[cfg(feature = "eh1_0_alpha")]
@@ -28,6 +58,11 @@ use embedded_hal::spi::{FullDuplex, Mode, Phase, Polarity};
 use embedded_time::rate::*;
 use pac::RESETS;
 
+#[cfg(feature = "async")]
+use crate::async_utils::{wait_until, WakerCell};
+#[cfg(feature = "async")]
+use core::cell::Cell;
+
 /// State of the SPI
 pub trait State {}
 
@@ -45,10 +80,25 @@ impl State for Disabled {}
 impl State for Enabled {}
 
 /// Pac SPI device
-pub trait SpiDevice: Deref<Target = pac::spi0::RegisterBlock> + SubsystemReset {}
+pub trait SpiDevice: Deref<Target = pac::spi0::RegisterBlock> + SubsystemReset {
+    /// Index of this device, 0 for SPI0 and 1 for SPI1
+    const ID: usize;
+    /// The DREQ value for the TX FIFO of this device
+    const TX_DREQ: u8;
+    /// The DREQ value for the RX FIFO of this device
+    const RX_DREQ: u8;
+}
 
-impl SpiDevice for pac::SPI0 {}
-impl SpiDevice for pac::SPI1 {}
+impl SpiDevice for pac::SPI0 {
+    const ID: usize = 0;
+    const TX_DREQ: u8 = crate::dma::DREQ_SPI0_TX;
+    const RX_DREQ: u8 = crate::dma::DREQ_SPI0_RX;
+}
+impl SpiDevice for pac::SPI1 {
+    const ID: usize = 1;
+    const TX_DREQ: u8 = crate::dma::DREQ_SPI1_TX;
+    const RX_DREQ: u8 = crate::dma::DREQ_SPI1_RX;
+}
 
 /// Data size used in spi
 pub trait DataSize {}
@@ -184,6 +234,155 @@ impl<D: SpiDevice, const DS: u8> Spi<Enabled, D, DS> {
 
         self.transition(Disabled { __private: () })
     }
+
+    /// Wait until the last word has been shifted out, then discard everything that was received
+    /// in the meantime.
+    fn flush_rx(&mut self) {
+        while self.device.sspsr.read().bsy().bit_is_set() {}
+        while self.is_readable() {
+            let _ = self.device.sspdr.read();
+        }
+        // The receive FIFO has most likely overflown while nobody was reading it.
+        self.device.sspicr.write(|w| w.roric().set_bit());
+    }
+
+    /// Start writing `buf` using the DMA channel `ch`.
+    ///
+    /// The data received while writing is discarded. Call [`WriteDmaTransfer::wait`] to block
+    /// until the transfer has completed and to get the channel, the buffer and the SPI driver back.
+    pub fn write_dma<CH, BUF, WORD>(self, ch: CH, buf: BUF) -> WriteDmaTransfer<CH, BUF, D, DS>
+    where
+        CH: SingleChannel,
+        BUF: ReadTarget<ReceivedWord = WORD>,
+        Self: WriteTarget<TransmittedWord = WORD>,
+    {
+        WriteDmaTransfer {
+            transfer: single_buffer::Config::new(ch, buf, self).start(),
+        }
+    }
+
+    /// Start sending `tx_buf` while receiving into `rx_buf` using a pair of DMA channels.
+    ///
+    /// The first channel feeds the TX FIFO, the second one drains the RX FIFO. Both buffers
+    /// should have the same length, as the transfer only completes once `rx_buf` is full.
+    pub fn transfer_dma<CH1, CH2, TXBUF, RXBUF, WORD>(
+        self,
+        channels: (CH1, CH2),
+        tx_buf: TXBUF,
+        rx_buf: RXBUF,
+    ) -> bidirectional::Transfer<CH1, CH2, TXBUF, Self, RXBUF>
+    where
+        CH1: SingleChannel,
+        CH2: SingleChannel,
+        TXBUF: ReadTarget<ReceivedWord = WORD>,
+        RXBUF: WriteTarget<TransmittedWord = WORD>,
+        Self: ReadTarget<ReceivedWord = WORD> + WriteTarget<TransmittedWord = WORD>,
+    {
+        bidirectional::Config::new(channels, tx_buf, self, rx_buf).start()
+    }
+}
+
+/// Depth of the TX and RX FIFOs
+#[cfg(feature = "async")]
+const FIFO_DEPTH: usize = 8;
+
+#[cfg(feature = "async")]
+static WAKERS: [WakerCell; 2] = [WakerCell::new(), WakerCell::new()];
+
+/// Wake the task waiting in one of the async transfers of [`Spi`].
+///
+/// Call this from the SPIx IRQ handler of the device `D`, e.g.
+/// `handle_async_interrupt::<pac::SPI0>()`. The receive interrupts are disabled until the next
+/// time the task has to wait.
+#[cfg(feature = "async")]
+pub fn handle_async_interrupt<D: SpiDevice>() {
+    // Safety: Only the interrupt mask is modified, which the waiting task sets up again.
+    let rb = unsafe {
+        &*if D::ID == 0 {
+            pac::SPI0::ptr()
+        } else {
+            pac::SPI1::ptr()
+        }
+    };
+    rb.sspimsc
+        .modify(|_, w| w.rxim().clear_bit().rtim().clear_bit());
+    WAKERS[D::ID].wake();
+}
+
+#[cfg(feature = "async")]
+impl<D: SpiDevice, const DS: u8> Spi<Enabled, D, DS> {
+    /// Shift `len` words, taking the words to send from `tx` and handing the received ones to
+    /// `rx`.
+    ///
+    /// At most [`FIFO_DEPTH`] words are in flight, so the RX FIFO can not overflow. While the
+    /// FIFOs are busy, the task waits for the receive or receive timeout interrupt.
+    async fn transfer_words_async(
+        &mut self,
+        len: usize,
+        tx: impl Fn(usize) -> u16,
+        mut rx: impl FnMut(usize, u16),
+    ) {
+        let mut sent = 0;
+        let mut received = 0;
+        while received < len {
+            while sent < len && sent - received < FIFO_DEPTH && self.is_writable() {
+                self.device
+                    .sspdr
+                    .write(|w| unsafe { w.data().bits(tx(sent)) });
+                sent += 1;
+            }
+            while received < sent && self.is_readable() {
+                rx(received, self.device.sspdr.read().data().bits());
+                received += 1;
+            }
+            if received < len {
+                let device = &self.device;
+                wait_until(
+                    &WAKERS[D::ID],
+                    || device.sspsr.read().rne().bit_is_set(),
+                    || {
+                        device
+                            .sspimsc
+                            .modify(|_, w| w.rxim().set_bit().rtim().set_bit())
+                    },
+                )
+                .await
+            }
+        }
+    }
+}
+
+/// Instance of a DMA write started by [`Spi::write_dma`]
+pub struct WriteDmaTransfer<CH, BUF, D, const DS: u8>
+where
+    CH: SingleChannel,
+    BUF: ReadTarget,
+    D: SpiDevice,
+    Spi<Enabled, D, DS>: WriteTarget,
+{
+    transfer: single_buffer::Transfer<CH, BUF, Spi<Enabled, D, DS>>,
+}
+
+impl<CH, BUF, D, WORD, const DS: u8> WriteDmaTransfer<CH, BUF, D, DS>
+where
+    CH: SingleChannel,
+    BUF: ReadTarget<ReceivedWord = WORD>,
+    D: SpiDevice,
+    Spi<Enabled, D, DS>: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Returns whether the DMA channel has written the whole buffer into the TX FIFO.
+    ///
+    /// The last words may still be in the process of being shifted out.
+    pub fn is_done(&self) -> bool {
+        self.transfer.is_done()
+    }
+
+    /// Block until all data has been sent, returning the channel, the buffer and the SPI driver.
+    pub fn wait(self) -> (CH, BUF, Spi<Enabled, D, DS>) {
+        let (ch, buf, mut spi) = self.transfer.wait();
+        spi.flush_rx();
+        (ch, buf, spi)
+    }
 }
 
 /// Same as core::convert::Infallible, but implementing spi::Error
@@ -238,6 +437,46 @@ macro_rules! impl_write {
             }
         }
 
+        // Safety: The SSPDR register is a FIFO, so the address stays the same for the whole
+        // transfer.
+        unsafe impl<D: SpiDevice> ReadTarget for Spi<Enabled, D, $nr> {
+            type ReceivedWord = $type;
+
+            fn rx_treq(&self) -> Option<u8> {
+                Some(D::RX_DREQ)
+            }
+
+            fn rx_address_count(&self) -> (u32, u32) {
+                (self.device.sspdr.as_ptr() as u32, u32::MAX)
+            }
+
+            fn rx_increment(&self) -> bool {
+                false
+            }
+        }
+
+        impl<D: SpiDevice> EndlessReadTarget for Spi<Enabled, D, $nr> {}
+
+        // Safety: The SSPDR register is a FIFO, so the address stays the same for the whole
+        // transfer.
+        unsafe impl<D: SpiDevice> WriteTarget for Spi<Enabled, D, $nr> {
+            type TransmittedWord = $type;
+
+            fn tx_treq(&self) -> Option<u8> {
+                Some(D::TX_DREQ)
+            }
+
+            fn tx_address_count(&mut self) -> (u32, u32) {
+                (self.device.sspdr.as_ptr() as u32, u32::MAX)
+            }
+
+            fn tx_increment(&self) -> bool {
+                false
+            }
+        }
+
+        impl<D: SpiDevice> EndlessWriteTarget for Spi<Enabled, D, $nr> {}
+
         impl<D: SpiDevice> spi::write::Default<$type> for Spi<Enabled, D, $nr> {}
         impl<D: SpiDevice> spi::transfer::Default<$type> for Spi<Enabled, D, $nr> {}
         impl<D: SpiDevice> spi::write_iter::Default<$type> for Spi<Enabled, D, $nr> {}
@@ -247,6 +486,125 @@ macro_rules! impl_write {
             type Error = SpiInfallible;
         }
 
+        #[cfg(feature = "async")]
+        impl<D: SpiDevice> Spi<Enabled, D, $nr> {
+            /// Send `write` while receiving into `read`.
+            ///
+            /// The longer buffer determines the length of the transfer: missing words are sent
+            /// as `0` and surplus received words are discarded. [`handle_async_interrupt`] must
+            /// be called from the SPIx IRQ handler.
+            pub async fn transfer_async(&mut self, read: &mut [$type], write: &[$type]) {
+                let len = read.len().max(write.len());
+                self.transfer_words_async(
+                    len,
+                    |i| write.get(i).map_or(0, |&word| word as u16),
+                    |i, word| {
+                        if let Some(slot) = read.get_mut(i) {
+                            *slot = word as $type;
+                        }
+                    },
+                )
+                .await
+            }
+
+            /// Send `words`, replacing them with the received words.
+            ///
+            /// [`handle_async_interrupt`] must be called from the SPIx IRQ handler.
+            pub async fn transfer_in_place_async(&mut self, words: &mut [$type]) {
+                let words = Cell::from_mut(words).as_slice_of_cells();
+                self.transfer_words_async(
+                    words.len(),
+                    |i| words[i].get() as u16,
+                    |i, word| words[i].set(word as $type),
+                )
+                .await
+            }
+
+            /// Send `words`, discarding the received data.
+            ///
+            /// [`handle_async_interrupt`] must be called from the SPIx IRQ handler.
+            pub async fn write_async(&mut self, words: &[$type]) {
+                self.transfer_async(&mut [], words).await
+            }
+
+            /// Receive into `words`, sending `0`.
+            ///
+            /// [`handle_async_interrupt`] must be called from the SPIx IRQ handler.
+            pub async fn read_async(&mut self, words: &mut [$type]) {
+                self.transfer_async(words, &[]).await
+            }
+        }
+
+        #[cfg(feature = "eh1_0_async")]
+        impl<D: SpiDevice> embedded_hal_async::spi::SpiBusFlush for Spi<Enabled, D, $nr> {
+            type FlushFuture<'a> = core::future::Ready<Result<(), Self::Error>> where Self: 'a;
+
+            fn flush<'a>(&'a mut self) -> Self::FlushFuture<'a> {
+                // The async transfers only complete once every word has been received.
+                core::future::ready(Ok(()))
+            }
+        }
+
+        #[cfg(feature = "eh1_0_async")]
+        impl<D: SpiDevice> embedded_hal_async::spi::SpiBusRead<$type> for Spi<Enabled, D, $nr> {
+            type ReadFuture<'a> = impl core::future::Future<Output = Result<(), Self::Error>> + 'a
+            where
+                Self: 'a;
+
+            fn read<'a>(&'a mut self, words: &'a mut [$type]) -> Self::ReadFuture<'a> {
+                async move {
+                    self.read_async(words).await;
+                    Ok(())
+                }
+            }
+        }
+
+        #[cfg(feature = "eh1_0_async")]
+        impl<D: SpiDevice> embedded_hal_async::spi::SpiBusWrite<$type> for Spi<Enabled, D, $nr> {
+            type WriteFuture<'a> = impl core::future::Future<Output = Result<(), Self::Error>> + 'a
+            where
+                Self: 'a;
+
+            fn write<'a>(&'a mut self, words: &'a [$type]) -> Self::WriteFuture<'a> {
+                async move {
+                    self.write_async(words).await;
+                    Ok(())
+                }
+            }
+        }
+
+        #[cfg(feature = "eh1_0_async")]
+        impl<D: SpiDevice> embedded_hal_async::spi::SpiBus<$type> for Spi<Enabled, D, $nr> {
+            type TransferFuture<'a> = impl core::future::Future<Output = Result<(), Self::Error>> + 'a
+            where
+                Self: 'a;
+
+            fn transfer<'a>(
+                &'a mut self,
+                read: &'a mut [$type],
+                write: &'a [$type],
+            ) -> Self::TransferFuture<'a> {
+                async move {
+                    self.transfer_async(read, write).await;
+                    Ok(())
+                }
+            }
+
+            type TransferInPlaceFuture<'a> = impl core::future::Future<Output = Result<(), Self::Error>> + 'a
+            where
+                Self: 'a;
+
+            fn transfer_in_place<'a>(
+                &'a mut self,
+                words: &'a mut [$type],
+            ) -> Self::TransferInPlaceFuture<'a> {
+                async move {
+                    self.transfer_in_place_async(words).await;
+                    Ok(())
+                }
+            }
+        }
+
         #[cfg(feature = "eh1_0_alpha")]
         impl<D: SpiDevice> eh1::nb::FullDuplex<$type> for Spi<Enabled, D, $nr> {
             fn read(&mut self) -> Result<$type, nb::Error<SpiInfallible>> {
@@ -1,3 +1,320 @@
 //! Synchronous Serial Interface (SSI)
-// See [Chapter 4 Section 10](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) for more details
-// TODO
+//!
+//! See [Chapter 4 Section 10](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) for more details
+//!
+//! The SSI connects the QSPI flash to the execute-in-place (XIP) cache. This driver can
+//! temporarily take the SSI out of XIP mode to issue raw SPI commands to the flash chip, for
+//! example to identify the flash part:
+//!
+//! ```no_run
+//! use rp2040_hal::{pac, ssi::Ssi};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let mut ssi = Ssi::new(peripherals.XIP_SSI);
+//! // Safety: Core 1 is not running, so nothing else accesses the flash.
+//! let [manufacturer, memory_type, capacity] = unsafe { ssi.read_jedec_id() };
+//! // The capacity is usually given as log2 of the size in bytes, e.g. 0x18 for 16MB.
+//! let flash_size = 1u32 << capacity;
+//! ```
+//!
+//! While a command is in progress, the flash can not be used to execute code or read data. The
+//! code doing the transfer is therefore placed in RAM, and interrupts are disabled on the calling
+//! core. The caller has to make sure that the other core does not access the flash either.
+//!
+//! Afterwards, XIP is re-enabled using a copy of the second stage bootloader taken when the
+//! driver was created, which restores the fast XIP configuration of the board.
+
+use crate::ram_register_access as ram;
+use crate::rom_data;
+use core::ptr::read_volatile;
+
+/// Size of the second stage bootloader at the start of the flash, in words
+const BOOT2_SIZE_WORDS: usize = 64;
+
+/// Start of the XIP address window, where the second stage bootloader is stored
+const XIP_BASE: *const u32 = 0x1000_0000 as _;
+
+/// Offset of the GPIO_QSPI_SS_CTRL register from the start of IO_QSPI
+const GPIO_QSPI_SS_CTRL_OFFSET: usize = 0x0c;
+/// Mask of the OUTOVER field in GPIO_QSPI_SS_CTRL
+const OUTOVER_MASK: u32 = 0x3 << 8;
+/// OUTOVER value to drive the chip select low
+const OUTOVER_LOW: u32 = 0x2 << 8;
+/// OUTOVER value to drive the chip select high
+const OUTOVER_HIGH: u32 = 0x3 << 8;
+
+/// Size of the blocks erased by [`BLOCK_ERASE_CMD`]
+const BLOCK_SIZE: u32 = 1 << 16;
+/// Command to erase a 64kB block, supported by most flash chips
+const BLOCK_ERASE_CMD: u8 = 0xd8;
+
+/// SR.TFNF: transmit FIFO not full
+const SR_TFNF: u32 = 1 << 1;
+/// SR.RFNE: receive FIFO not empty
+const SR_RFNE: u32 = 1 << 3;
+
+/// Number of bytes which can be in flight without overflowing the 16 entry receive FIFO
+const MAX_IN_FLIGHT: usize = 16 - 2;
+
+/// Status registers of SPI NOR flash chips
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StatusRegister {
+    /// Status register 1, read with command 05h
+    Status1,
+    /// Status register 2, read with command 35h
+    Status2,
+    /// Status register 3, read with command 15h
+    Status3,
+}
+
+impl StatusRegister {
+    fn command(self) -> u8 {
+        match self {
+            StatusRegister::Status1 => 0x05,
+            StatusRegister::Status2 => 0x35,
+            StatusRegister::Status3 => 0x15,
+        }
+    }
+}
+
+/// Pointers to the functions and registers used while XIP is disabled.
+///
+/// All of them are looked up before leaving XIP mode, as the lookup code runs from flash.
+struct XipContext {
+    connect_internal_flash: unsafe extern "C" fn(),
+    flash_exit_xip: unsafe extern "C" fn(),
+    flash_flush_cache: unsafe extern "C" fn(),
+    flash_range_erase: unsafe extern "C" fn(u32, usize, u32, u8),
+    flash_range_program: unsafe extern "C" fn(u32, *const u8, usize),
+    ss_ctrl: *mut u32,
+    sr: *const u32,
+    dr0: *mut u32,
+    boot2: *const u32,
+}
+
+/// SSI driver for issuing raw commands to the QSPI flash
+pub struct Ssi {
+    device: pac::XIP_SSI,
+    boot2: [u32; BOOT2_SIZE_WORDS],
+}
+
+impl Ssi {
+    /// Create a new SSI driver.
+    ///
+    /// This takes a copy of the second stage bootloader, which is used to restore XIP mode after
+    /// each command.
+    pub fn new(device: pac::XIP_SSI) -> Self {
+        let mut boot2 = [0; BOOT2_SIZE_WORDS];
+        for (i, word) in boot2.iter_mut().enumerate() {
+            // Safety: The second stage bootloader is always mapped at the start of the flash.
+            *word = unsafe { read_volatile(XIP_BASE.add(i)) };
+        }
+        Self { device, boot2 }
+    }
+
+    /// Free the underlying device.
+    pub fn free(self) -> pac::XIP_SSI {
+        self.device
+    }
+
+    /// Send `tx` to the flash, then receive `rx.len()` bytes into `rx`.
+    ///
+    /// The chip select is asserted for the whole command. Bytes received while `tx` is being
+    /// sent are discarded, and zeroes are sent while `rx` is being received.
+    ///
+    /// # Safety
+    ///
+    /// Nothing else may access the flash while the command is in progress. In particular, the
+    /// other core must not execute code from flash or read data from it. `tx` and `rx` must not
+    /// be located in flash.
+    pub unsafe fn flash_do_cmd(&mut self, tx: &[u8], rx: &mut [u8]) {
+        let ctx = self.xip_context();
+        cortex_m::interrupt::free(|_| {
+            flash_do_cmd_in_ram(&ctx, tx.as_ptr(), tx.len(), rx.as_mut_ptr(), rx.len())
+        });
+    }
+
+    /// Erase `count` bytes of flash starting at `addr`, which is an offset from the start of the
+    /// flash.
+    ///
+    /// `addr` and `count` must be multiples of the 4096 byte sector size. 64kB block erases are
+    /// used where possible.
+    ///
+    /// # Safety
+    ///
+    /// See [`flash_do_cmd`](Ssi::flash_do_cmd). The erased range must not contain code or data
+    /// which is still in use.
+    pub unsafe fn flash_range_erase(&mut self, addr: u32, count: usize) {
+        let ctx = self.xip_context();
+        cortex_m::interrupt::free(|_| flash_range_erase_in_ram(&ctx, addr, count));
+    }
+
+    /// Program `data` to the flash starting at `addr`, which is an offset from the start of the
+    /// flash.
+    ///
+    /// `addr` and the length of `data` must be multiples of the 256 byte page size. The range
+    /// has to be erased beforehand.
+    ///
+    /// # Safety
+    ///
+    /// See [`flash_do_cmd`](Ssi::flash_do_cmd). `data` must not be located in flash.
+    pub unsafe fn flash_range_program(&mut self, addr: u32, data: &[u8]) {
+        let ctx = self.xip_context();
+        cortex_m::interrupt::free(|_| {
+            flash_range_program_in_ram(&ctx, addr, data.as_ptr(), data.len())
+        });
+    }
+
+    fn xip_context(&self) -> XipContext {
+        XipContext {
+            connect_internal_flash: rom_data::connect_internal_flash::ptr(),
+            flash_exit_xip: rom_data::flash_exit_xip::ptr(),
+            flash_flush_cache: rom_data::flash_flush_cache::ptr(),
+            flash_range_erase: rom_data::flash_range_erase::ptr(),
+            flash_range_program: rom_data::flash_range_program::ptr(),
+            ss_ctrl: (pac::IO_QSPI::ptr() as usize + GPIO_QSPI_SS_CTRL_OFFSET) as *mut u32,
+            sr: self.device.sr.as_ptr(),
+            dr0: self.device.dr0.as_ptr(),
+            boot2: self.boot2.as_ptr(),
+        }
+    }
+
+    /// Read the JEDEC ID of the flash: manufacturer ID, memory type and capacity.
+    ///
+    /// # Safety
+    ///
+    /// See [`flash_do_cmd`](Ssi::flash_do_cmd).
+    pub unsafe fn read_jedec_id(&mut self) -> [u8; 3] {
+        let mut id = [0; 3];
+        self.flash_do_cmd(&[0x9f], &mut id);
+        id
+    }
+
+    /// Read the 64 bit unique ID of the flash.
+    ///
+    /// Not all flash parts support this command.
+    ///
+    /// # Safety
+    ///
+    /// See [`flash_do_cmd`](Ssi::flash_do_cmd).
+    pub unsafe fn read_unique_id(&mut self) -> [u8; 8] {
+        let mut id = [0; 8];
+        // The command is followed by four dummy bytes.
+        self.flash_do_cmd(&[0x4b, 0, 0, 0, 0], &mut id);
+        id
+    }
+
+    /// Read one of the status registers of the flash.
+    ///
+    /// # Safety
+    ///
+    /// See [`flash_do_cmd`](Ssi::flash_do_cmd).
+    pub unsafe fn read_status(&mut self, register: StatusRegister) -> u8 {
+        let mut status = [0; 1];
+        self.flash_do_cmd(&[register.command()], &mut status);
+        status[0]
+    }
+
+    /// Read the Serial Flash Discoverable Parameters (SFDP) starting at `address`.
+    ///
+    /// # Safety
+    ///
+    /// See [`flash_do_cmd`](Ssi::flash_do_cmd).
+    pub unsafe fn read_sfdp(&mut self, address: u32, buf: &mut [u8]) {
+        let [_, a2, a1, a0] = address.to_be_bytes();
+        // The address is followed by one dummy byte.
+        self.flash_do_cmd(&[0x5a, a2, a1, a0, 0], buf);
+    }
+}
+
+/// Perform a raw flash command with XIP disabled.
+///
+/// This function is placed in RAM and must not call anything located in flash, so it only uses
+/// the function pointers in `ctx` and the always inlined register accesses of
+/// [`ram_register_access`](crate::ram_register_access), also in unoptimized builds.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn flash_do_cmd_in_ram(
+    ctx: &XipContext,
+    tx: *const u8,
+    tx_len: usize,
+    rx: *mut u8,
+    rx_len: usize,
+) {
+    exit_xip(ctx);
+
+    // Force the chip select low for the whole command.
+    let ss_ctrl = ram::read_u32(ctx.ss_ctrl);
+    ram::write_u32(ctx.ss_ctrl, (ss_ctrl & !OUTOVER_MASK) | OUTOVER_LOW);
+
+    let count = tx_len + rx_len;
+    let mut tx_remaining = count;
+    let mut rx_remaining = count;
+    while tx_remaining > 0 || rx_remaining > 0 {
+        let status = ram::read_u32(ctx.sr);
+        if status & SR_TFNF != 0 && tx_remaining > 0 && rx_remaining - tx_remaining < MAX_IN_FLIGHT
+        {
+            let index = count - tx_remaining;
+            let byte = if index < tx_len {
+                ram::read_u8((tx as usize + index) as *const u8)
+            } else {
+                0
+            };
+            ram::write_u32(ctx.dr0, byte as u32);
+            tx_remaining -= 1;
+        }
+        if status & SR_RFNE != 0 && rx_remaining > 0 {
+            let index = count - rx_remaining;
+            let byte = ram::read_u32(ctx.dr0) as u8;
+            if index >= tx_len {
+                ram::write_u8((rx as usize + index - tx_len) as *mut u8, byte);
+            }
+            rx_remaining -= 1;
+        }
+    }
+
+    // Release the chip select.
+    let ss_ctrl = ram::read_u32(ctx.ss_ctrl);
+    ram::write_u32(ctx.ss_ctrl, (ss_ctrl & !OUTOVER_MASK) | OUTOVER_HIGH);
+
+    enter_xip(ctx);
+}
+
+/// Erase a range of flash with XIP disabled.
+///
+/// See [`flash_do_cmd_in_ram`] for the restrictions on this function.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn flash_range_erase_in_ram(ctx: &XipContext, addr: u32, count: usize) {
+    exit_xip(ctx);
+    (ctx.flash_range_erase)(addr, count, BLOCK_SIZE, BLOCK_ERASE_CMD);
+    enter_xip(ctx);
+}
+
+/// Program a range of flash with XIP disabled.
+///
+/// See [`flash_do_cmd_in_ram`] for the restrictions on this function.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn flash_range_program_in_ram(ctx: &XipContext, addr: u32, data: *const u8, count: usize) {
+    exit_xip(ctx);
+    (ctx.flash_range_program)(addr, data, count);
+    enter_xip(ctx);
+}
+
+/// Take the SSI out of XIP mode so that it can be used for serial commands.
+#[inline(always)]
+unsafe fn exit_xip(ctx: &XipContext) {
+    (ctx.connect_internal_flash)();
+    (ctx.flash_exit_xip)();
+}
+
+/// Flush the XIP cache and return the SSI to XIP mode.
+#[inline(always)]
+unsafe fn enter_xip(ctx: &XipContext) {
+    (ctx.flash_flush_cache)();
+
+    // Run the copy of the second stage bootloader to restore the fast XIP configuration. The
+    // lowest bit of the address selects the Thumb instruction set.
+    let boot2: extern "C" fn() = core::mem::transmute(ctx.boot2 as usize + 1);
+    boot2();
+}
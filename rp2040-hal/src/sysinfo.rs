@@ -0,0 +1,154 @@
+//! Reset reason and chip information
+//!
+//! The cause of the last reset is recorded in `VREG_AND_CHIP_RESET.CHIP_RESET` for resets of the
+//! whole chip, and in `WATCHDOG.REASON` for resets triggered by the watchdog.
+//!
+//! ```no_run
+//! use rp2040_hal::sysinfo::{self, ResetReason};
+//!
+//! match sysinfo::reset_reason() {
+//!     Some(ResetReason::WatchdogTimeout) => { /* the firmware got stuck */ }
+//!     Some(ResetReason::PowerOnOrBrownOut) => { /* normal start, or the supply dropped */ }
+//!     _ => {}
+//! }
+//! let revision = sysinfo::chip_id().revision;
+//! ```
+//!
+//! Resets which only restart the processors, e.g. `SCB::sys_reset`, are not recorded. The
+//! reason of the reset before is reported in that case.
+//!
+//! These are read only status registers, so they can be read at any time without owning the
+//! peripherals.
+
+/// Cause of the last reset
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ResetReason {
+    /// Power-on reset, or the brown-out detector. The chip can not tell these apart.
+    PowerOnOrBrownOut,
+    /// The RUN pin was pulled low
+    RunPin,
+    /// Reset from the debug port
+    Debugger,
+    /// The watchdog timer ran out
+    WatchdogTimeout,
+    /// The watchdog reset was forced through `WATCHDOG.CTRL.TRIGGER`
+    WatchdogForced,
+}
+
+/// `HAD_POR` bit of `CHIP_RESET`
+const HAD_POR: u32 = 1 << 8;
+/// `HAD_RUN` bit of `CHIP_RESET`
+const HAD_RUN: u32 = 1 << 16;
+/// `HAD_PSM_RESTART` bit of `CHIP_RESET`
+const HAD_PSM_RESTART: u32 = 1 << 20;
+/// `TIMER` bit of `WATCHDOG.REASON`
+const REASON_TIMER: u32 = 1 << 0;
+/// `FORCE` bit of `WATCHDOG.REASON`
+const REASON_FORCE: u32 = 1 << 1;
+
+/// Cause of the last reset, or `None` if no reason was recorded.
+pub fn reset_reason() -> Option<ResetReason> {
+    // Safety: Read only access to status registers.
+    let (watchdog, vreg) = unsafe { (&*pac::WATCHDOG::ptr(), &*pac::VREG_AND_CHIP_RESET::ptr()) };
+
+    // A hardware reset clears WATCHDOG.REASON, while a watchdog reset leaves CHIP_RESET alone.
+    let reason = watchdog.reason.read().bits();
+    if reason & REASON_FORCE != 0 {
+        return Some(ResetReason::WatchdogForced);
+    }
+    if reason & REASON_TIMER != 0 {
+        return Some(ResetReason::WatchdogTimeout);
+    }
+
+    let chip_reset = vreg.chip_reset.read().bits();
+    if chip_reset & HAD_PSM_RESTART != 0 {
+        Some(ResetReason::Debugger)
+    } else if chip_reset & HAD_RUN != 0 {
+        Some(ResetReason::RunPin)
+    } else if chip_reset & HAD_POR != 0 {
+        Some(ResetReason::PowerOnOrBrownOut)
+    } else {
+        None
+    }
+}
+
+/// Silicon revision of the RP2040
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ChipRevision {
+    /// RP2040-B0
+    B0,
+    /// RP2040-B1
+    B1,
+    /// RP2040-B2
+    B2,
+    /// A revision unknown to this HAL, with the raw `REVISION` field
+    Unknown(u8),
+}
+
+/// Contents of `SYSINFO.CHIP_ID`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChipId {
+    /// JEDEC manufacturer ID
+    pub manufacturer: u16,
+    /// Part number
+    pub part: u16,
+    /// Silicon revision
+    pub revision: ChipRevision,
+}
+
+/// Read the manufacturer, part number and silicon revision of the chip.
+pub fn chip_id() -> ChipId {
+    // Safety: Read only access to a status register.
+    let chip_id = unsafe { (*pac::SYSINFO::ptr()).chip_id.read().bits() };
+    decode_chip_id(chip_id, crate::rom_data::rom_version_number())
+}
+
+/// Decode the raw `SYSINFO.CHIP_ID` value.
+///
+/// `REVISION` reads 1 on both B0 and B1, they are told apart by the boot ROM version, which is
+/// 1 on B0 and 2 on B1. B2 reads 2.
+fn decode_chip_id(chip_id: u32, rom_version: u8) -> ChipId {
+    ChipId {
+        manufacturer: (chip_id & 0xfff) as u16,
+        part: ((chip_id >> 12) & 0xffff) as u16,
+        revision: match (chip_id >> 28) as u8 {
+            1 if rom_version < 2 => ChipRevision::B0,
+            1 => ChipRevision::B1,
+            2 => ChipRevision::B2,
+            revision => ChipRevision::Unknown(revision),
+        },
+    }
+}
+
+/// Git hash of the chip source, as stored in `SYSINFO.GITREF_RP2040`.
+pub fn git_ref() -> u32 {
+    // Safety: Read only access to a status register.
+    unsafe { (*pac::SYSINFO::ptr()).gitref_rp2040.read().bits() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_chip_id() {
+        let chip_id = |revision: u32| (revision << 28) | (0x0002 << 12) | 0x927;
+        assert_eq!(
+            decode_chip_id(chip_id(1), 1),
+            ChipId {
+                manufacturer: 0x927,
+                part: 0x0002,
+                revision: ChipRevision::B0,
+            }
+        );
+        assert_eq!(decode_chip_id(chip_id(1), 2).revision, ChipRevision::B1);
+        assert_eq!(decode_chip_id(chip_id(2), 3).revision, ChipRevision::B2);
+        assert_eq!(
+            decode_chip_id(chip_id(3), 4).revision,
+            ChipRevision::Unknown(3)
+        );
+    }
+}
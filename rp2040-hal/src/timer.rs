@@ -6,6 +6,10 @@
 //!
 //! Each of the 4 alarms can match on the lower 32 bits of Counter and trigger an interrupt.
 //!
+//! Points in time and time spans are represented by the [`Instant`] and [`Duration`] types, which
+//! are [`fugit`](https://docs.rs/fugit) types with a 1 MHz tick rate. The [`monotonic`] module
+//! provides a monotonic timer for [RTIC](https://rtic.rs), using one of the alarms.
+//!
 //! See [Chapter 4 Section 6](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) of the datasheet for more details.
 
 use embedded_time::duration::Microseconds;
@@ -15,6 +19,34 @@ use crate::pac::{RESETS, TIMER};
 use crate::resets::SubsystemReset;
 use core::marker::PhantomData;
 
+pub mod monotonic;
+
+#[cfg(feature = "async")]
+pub mod queue;
+#[cfg(feature = "async")]
+pub use queue::{handle_async_interrupt, Delay, TimerQueue};
+
+/// Point in time, in microseconds since the timer was started
+pub type Instant = fugit::TimerInstantU64<1_000_000>;
+
+/// Time span with microsecond resolution
+pub type Duration = fugit::TimerDurationU64<1_000_000>;
+
+/// Read the 64-bit counter without latching, so it can be used from any context.
+fn counter() -> u64 {
+    // Safety: Only the raw counter registers are read, which has no side effects.
+    let timer = unsafe { &*TIMER::ptr() };
+    let mut hi0 = timer.timerawh.read().bits();
+    loop {
+        let low = timer.timerawl.read().bits();
+        let hi1 = timer.timerawh.read().bits();
+        if hi0 == hi1 {
+            break (u64::from(hi0) << 32) | u64::from(low);
+        }
+        hi0 = hi1;
+    }
+}
+
 /// Timer peripheral
 pub struct Timer {
     timer: TIMER,
@@ -33,15 +65,12 @@ impl Timer {
 
     /// Get the current counter value.
     pub fn get_counter(&self) -> u64 {
-        let mut hi0 = self.timer.timerawh.read().bits();
-        loop {
-            let low = self.timer.timerawl.read().bits();
-            let hi1 = self.timer.timerawh.read().bits();
-            if hi0 == hi1 {
-                break (u64::from(hi0) << 32) | u64::from(low);
-            }
-            hi0 = hi1;
-        }
+        counter()
+    }
+
+    /// Get the current counter value as an [`Instant`].
+    pub fn get_instant(&self) -> Instant {
+        Instant::from_ticks(counter())
     }
 
     /// Get the value of the least significant word of the counter.
@@ -49,6 +78,22 @@ impl Timer {
         self.timer.timerawl.read().bits()
     }
 
+    /// Wait for `us` microseconds.
+    ///
+    /// See the [`queue`] module for the setup needed to wait without busy polling.
+    #[cfg(feature = "async")]
+    pub fn delay_us(&self, us: u32) -> Delay {
+        Delay::until(self.get_counter() + u64::from(us))
+    }
+
+    /// Wait for `ms` milliseconds.
+    ///
+    /// See the [`queue`] module for the setup needed to wait without busy polling.
+    #[cfg(feature = "async")]
+    pub fn delay_ms(&self, ms: u32) -> Delay {
+        Delay::until(self.get_counter() + u64::from(ms) * 1000)
+    }
+
     /// Initialized a Count Down instance without starting it.
     pub fn count_down(&self) -> CountDown<'_> {
         CountDown {
@@ -203,6 +248,23 @@ pub trait Alarm {
         countdown: TIME,
     ) -> Result<(), ScheduleAlarmError>;
 
+    /// Schedule the alarm to be finished at `timestamp`. If [enable_interrupt] is called,
+    /// this will trigger interrupt when this time is reached.
+    ///
+    /// The alarm only compares the lower 32 bits of the counter, so a timestamp more than about
+    /// 71 minutes in the future finishes the alarm early. A timestamp in the past finishes the
+    /// alarm immediately.
+    ///
+    /// The default implementation schedules the alarm for the remaining time with [schedule],
+    /// but at least 10 microseconds.
+    ///
+    /// [enable_interrupt]: #method.enable_interrupt
+    /// [schedule]: #tymethod.schedule
+    fn schedule_at(&mut self, timestamp: Instant) -> Result<(), ScheduleAlarmError> {
+        let remaining = timestamp.ticks().saturating_sub(counter()).max(10);
+        self.schedule(Microseconds::new(remaining.min(u64::from(u32::MAX)) as u32))
+    }
+
     /// Return true if this alarm is finished.
     fn finished(&self) -> bool;
 }
@@ -226,6 +288,8 @@ macro_rules! impl_alarm {
                 unsafe {
                     let timer = &(*pac::TIMER::ptr());
                     timer.intr.write_with_zero(|w| w.$int_alarm().set_bit());
+                    // The interrupt might have been forced by `schedule_at`.
+                    write_bitmask_clear((&timer.intf).as_ptr(), $armed_bit_mask);
                 }
             }
 
@@ -293,6 +357,33 @@ macro_rules! impl_alarm {
                 }
             }
 
+            /// Schedule the alarm to be finished at `timestamp`. If [enable_interrupt] is called, this will trigger interrupt `
+            #[doc = $int_name]
+            /// ` when this time is reached.
+            ///
+            /// [enable_interrupt]: #method.enable_interrupt
+            fn schedule_at(&mut self, timestamp: Instant) -> Result<(), ScheduleAlarmError> {
+                let target_time = timestamp.ticks() as u32;
+                cortex_m::interrupt::free(|_| {
+                    // safety: This is the only code in the codebase that accesses memory address $timer_alarm
+                    let timer = unsafe { &*TIMER::ptr() };
+                    timer.$timer_alarm.write(|w| unsafe { w.bits(target_time) });
+
+                    // If the timestamp has already passed, the alarm would only fire once the
+                    // lower 32 bits of the counter wrap around. Disarm it and force the
+                    // interrupt instead.
+                    if counter() >= timestamp.ticks() && !self.finished() {
+                        // safety: ARMED is a write-clear register, and INTF is only written
+                        // through the atomic aliases, for the bit belonging to this alarm.
+                        unsafe {
+                            timer.armed.write(|w| w.bits($armed_bit_mask));
+                            write_bitmask_set((&timer.intf).as_ptr(), $armed_bit_mask);
+                        }
+                    }
+                });
+                Ok(())
+            }
+
             /// Return true if this alarm is finished.
             fn finished(&self) -> bool {
                 // safety: This is a read action and should not have any UB
@@ -300,6 +391,20 @@ macro_rules! impl_alarm {
                 (bits & $armed_bit_mask) == 0
             }
         }
+
+        #[cfg(feature = "async")]
+        impl $name {
+            /// Hand this alarm over to the async [timer queue](queue), which multiplexes any
+            /// number of delays on it. The handler of interrupt `
+            #[doc = $int_name]
+            /// ` must call [`handle_async_interrupt`].
+            pub fn into_timer_queue(mut self) -> TimerQueue {
+                self.clear_interrupt();
+                self.enable_interrupt();
+                queue::set_alarm(($armed_bit_mask as u32).trailing_zeros() as u8);
+                TimerQueue { _private: () }
+            }
+        }
     };
 }
 
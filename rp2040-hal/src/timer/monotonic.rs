@@ -0,0 +1,78 @@
+//! Monotonic timer for RTIC
+//!
+//! [`Monotonic`] implements [`rtic_monotonic::Monotonic`] on top of the [`Timer`] counter and one
+//! of its alarms, so tasks can be scheduled with `spawn_after` and `spawn_at`:
+//!
+//! ```ignore
+//! #[rtic::app(device = rp2040_hal::pac, peripherals = true, dispatchers = [TIMER_IRQ_1])]
+//! mod app {
+//!     use rp2040_hal::timer::{monotonic::Monotonic, Alarm0, Duration};
+//!
+//!     #[monotonic(binds = TIMER_IRQ_0, default = true)]
+//!     type Mono = Monotonic<Alarm0>;
+//!
+//!     #[init]
+//!     fn init(c: init::Context) -> (Shared, Local, init::Monotonics) {
+//!         let mut resets = c.device.RESETS;
+//!         let mut timer = rp2040_hal::Timer::new(c.device.TIMER, &mut resets);
+//!         let alarm = timer.alarm_0().unwrap();
+//!         tick::spawn_after(Duration::millis(500)).unwrap();
+//!         (Shared {}, Local {}, init::Monotonics(Monotonic::new(timer, alarm)))
+//!     }
+//!
+//!     #[task]
+//!     fn tick(_: tick::Context) {
+//!         tick::spawn_after(Duration::millis(500)).unwrap();
+//!     }
+//! }
+//! ```
+
+use super::{Alarm, Duration, Instant, Timer};
+
+/// RTIC monotonic timer, using the [`Timer`] counter and the alarm `A`
+pub struct Monotonic<A> {
+    timer: Timer,
+    alarm: A,
+}
+
+impl<A: Alarm> Monotonic<A> {
+    /// Create a new monotonic timer. The interrupt of `alarm` has to be bound to the monotonic
+    /// in the RTIC app.
+    pub fn new(timer: Timer, alarm: A) -> Self {
+        Self { timer, alarm }
+    }
+
+    /// Release the timer and the alarm.
+    pub fn free(self) -> (Timer, A) {
+        (self.timer, self.alarm)
+    }
+}
+
+impl<A: Alarm> rtic_monotonic::Monotonic for Monotonic<A> {
+    type Instant = Instant;
+    type Duration = Duration;
+
+    fn now(&mut self) -> Instant {
+        self.timer.get_instant()
+    }
+
+    fn set_compare(&mut self, instant: Instant) {
+        // `schedule_at` always succeeds. Timestamps too far in the future fire early, after
+        // which RTIC sets the compare value again.
+        let _ = self.alarm.schedule_at(instant);
+    }
+
+    fn clear_compare_flag(&mut self) {
+        self.alarm.clear_interrupt();
+    }
+
+    fn zero() -> Instant {
+        Instant::from_ticks(0)
+    }
+
+    unsafe fn reset(&mut self) {
+        // The counter keeps running from when the timer was brought out of reset, only the
+        // alarm needs to be set up.
+        self.alarm.enable_interrupt();
+    }
+}
@@ -0,0 +1,296 @@
+//! Async timer queue
+//!
+//! Requires the `async` feature. Any number of [`Delay`] futures share a single hardware alarm,
+//! which is handed over to the queue with `into_timer_queue`, e.g.
+//! [`Alarm0::into_timer_queue`](super::Alarm0::into_timer_queue). The returned [`TimerQueue`]
+//! waits for an [`Instant`], while [`Timer::delay_us`](super::Timer::delay_us) and
+//! [`Timer::delay_ms`](super::Timer::delay_ms) wait for a time span. The alarm interrupt has to
+//! be unmasked in the NVIC and its handler must call [`handle_async_interrupt`]:
+//!
+//! ```ignore
+//! use rp2040_hal::{pac::{self, interrupt}, timer};
+//!
+//! let mut pac = pac::Peripherals::take().unwrap();
+//! let mut timer = rp2040_hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+//! let queue = timer.alarm_0().unwrap().into_timer_queue();
+//! unsafe { pac::NVIC::unmask(pac::Interrupt::TIMER_IRQ_0) };
+//!
+//! #[interrupt]
+//! fn TIMER_IRQ_0() {
+//!     timer::handle_async_interrupt();
+//! }
+//!
+//! async fn blink(timer: &rp2040_hal::Timer) {
+//!     loop {
+//!         // toggle a LED...
+//!         timer.delay_ms(500).await;
+//!     }
+//! }
+//!
+//! async fn tick(timer: &rp2040_hal::Timer, queue: timer::TimerQueue) {
+//!     let mut next = timer.get_instant();
+//!     loop {
+//!         next += timer::Duration::millis(10);
+//!         queue.wait_until(next).await;
+//!     }
+//! }
+//! ```
+//!
+//! The queue has room for [`QUEUE_SIZE`] waiting futures. Futures that do not fit, or that are
+//! awaited before an alarm was handed to the queue, fall back to waking themselves on every poll.
+
+use super::{counter, Instant};
+use core::cell::UnsafeCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicU8, Ordering};
+use core::task::{Context, Poll, Waker};
+
+/// Number of [`Delay`] futures that can wait on the hardware alarm at the same time
+pub const QUEUE_SIZE: usize = 16;
+
+/// Value of [`ALARM`] while no alarm has been handed to the queue
+const NO_ALARM: u8 = 0xff;
+
+/// Index of the hardware alarm used by the queue
+static ALARM: AtomicU8 = AtomicU8::new(NO_ALARM);
+
+struct Slot {
+    deadline: u64,
+    waker: Option<Waker>,
+    used: bool,
+}
+
+const EMPTY_SLOT: Slot = Slot {
+    deadline: 0,
+    waker: None,
+    used: false,
+};
+
+struct Queue {
+    slots: UnsafeCell<[Slot; QUEUE_SIZE]>,
+}
+
+// Safety: The slots are only accessed from within a critical section.
+unsafe impl Sync for Queue {}
+
+static QUEUE: Queue = Queue {
+    slots: UnsafeCell::new([EMPTY_SLOT; QUEUE_SIZE]),
+};
+
+/// Run `f` on the queue slots within a critical section.
+fn with_slots<R>(f: impl FnOnce(&mut [Slot; QUEUE_SIZE]) -> R) -> R {
+    // Safety: We are in a critical section, and `f` can not leak the reference.
+    critical_section::with(|_| f(unsafe { &mut *QUEUE.slots.get() }))
+}
+
+/// Hand the hardware alarm with index `alarm` over to the queue.
+pub(super) fn set_alarm(alarm: u8) {
+    critical_section::with(|_| {
+        ALARM.store(alarm, Ordering::Relaxed);
+        with_slots(schedule);
+    });
+}
+
+/// Wake the futures whose deadline is at or before `now`, and return the earliest remaining
+/// deadline.
+///
+/// Woken futures keep their slot until they are polled again.
+fn wake_expired(slots: &mut [Slot; QUEUE_SIZE], now: u64) -> Option<u64> {
+    let mut next = None;
+    for slot in slots.iter_mut().filter(|slot| slot.used) {
+        if slot.deadline <= now {
+            if let Some(waker) = slot.waker.take() {
+                waker.wake();
+            }
+        } else if next.map_or(true, |next| slot.deadline < next) {
+            next = Some(slot.deadline);
+        }
+    }
+    next
+}
+
+/// Store `deadline` and `waker` in the slot with index `slot`, or in a free slot if `slot` is
+/// `None`. Returns the index of the slot, or `None` if the queue is full.
+fn enqueue(
+    slots: &mut [Slot; QUEUE_SIZE],
+    slot: Option<usize>,
+    deadline: u64,
+    waker: &Waker,
+) -> Option<usize> {
+    let index = slot.or_else(|| slots.iter().position(|slot| !slot.used))?;
+    let entry = &mut slots[index];
+    entry.used = true;
+    entry.deadline = deadline;
+    match &entry.waker {
+        Some(current) if current.will_wake(waker) => {}
+        _ => entry.waker = Some(waker.clone()),
+    }
+    Some(index)
+}
+
+/// Free the slot with index `index`.
+fn dequeue(slots: &mut [Slot; QUEUE_SIZE], index: usize) {
+    slots[index].used = false;
+    slots[index].waker = None;
+}
+
+/// Wake the expired futures and program the alarm for the earliest remaining deadline.
+fn schedule(slots: &mut [Slot; QUEUE_SIZE]) {
+    let alarm = ALARM.load(Ordering::Relaxed);
+    if alarm == NO_ALARM {
+        return;
+    }
+    loop {
+        let next = match wake_expired(slots, counter()) {
+            Some(next) => next,
+            None => return,
+        };
+        // The alarm only compares the lower 32 bits of the counter. A deadline further away
+        // than that causes an early interrupt, after which the alarm is programmed again.
+        // Safety: The alarm is owned by the queue.
+        let timer = unsafe { &*pac::TIMER::ptr() };
+        let target = next as u32;
+        match alarm {
+            0 => timer.alarm0.write(|w| unsafe { w.bits(target) }),
+            1 => timer.alarm1.write(|w| unsafe { w.bits(target) }),
+            2 => timer.alarm2.write(|w| unsafe { w.bits(target) }),
+            _ => timer.alarm3.write(|w| unsafe { w.bits(target) }),
+        }
+        // If the deadline passed while the alarm was being written, the alarm will not fire
+        // until the counter wraps around, so handle it right away.
+        if counter() < next {
+            return;
+        }
+    }
+}
+
+/// Wake the [`Delay`] futures whose deadline has passed.
+///
+/// Call this from the interrupt handler of the alarm that was handed to the timer queue.
+pub fn handle_async_interrupt() {
+    let alarm = ALARM.load(Ordering::Relaxed);
+    if alarm == NO_ALARM {
+        return;
+    }
+    // Safety: TIMER.intr is a write-clear register, and the alarm is owned by the queue.
+    unsafe {
+        (*pac::TIMER::ptr())
+            .intr
+            .write_with_zero(|w| w.bits(1 << alarm))
+    };
+    with_slots(schedule);
+}
+
+/// Future which completes once the timer counter reaches a deadline.
+///
+/// Returned by [`Timer::delay_us`](super::Timer::delay_us),
+/// [`Timer::delay_ms`](super::Timer::delay_ms) and [`TimerQueue::wait_until`].
+pub struct Delay {
+    deadline: u64,
+    slot: Option<usize>,
+}
+
+impl Delay {
+    pub(super) fn until(deadline: u64) -> Self {
+        Self {
+            deadline,
+            slot: None,
+        }
+    }
+
+    fn release(&mut self) {
+        if let Some(index) = self.slot.take() {
+            with_slots(|slots| dequeue(slots, index));
+        }
+    }
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        if counter() >= this.deadline {
+            this.release();
+            return Poll::Ready(());
+        }
+        let deadline = this.deadline;
+        let slot = this.slot;
+        let queued = with_slots(|slots| {
+            let index = enqueue(slots, slot, deadline, cx.waker())?;
+            schedule(slots);
+            Some(index)
+        });
+        this.slot = queued;
+        if queued.is_none() || ALARM.load(Ordering::Relaxed) == NO_ALARM {
+            cx.waker().wake_by_ref();
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Delay {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+/// The alarm handed over to the async timer queue, returned by `into_timer_queue`.
+#[derive(Clone, Copy)]
+pub struct TimerQueue {
+    pub(super) _private: (),
+}
+
+impl TimerQueue {
+    /// Wait until the timer reaches `instant`, e.g. an offset from
+    /// [`Timer::get_instant`](super::Timer::get_instant).
+    pub fn wait_until(&self, instant: Instant) -> Delay {
+        Delay::until(instant.ticks())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::task::noop_waker;
+
+    #[test]
+    fn test_wake_expired_ordering() {
+        let waker = noop_waker();
+        let mut slots = [EMPTY_SLOT; QUEUE_SIZE];
+        assert_eq!(enqueue(&mut slots, None, 300, &waker), Some(0));
+        assert_eq!(enqueue(&mut slots, None, 100, &waker), Some(1));
+        assert_eq!(enqueue(&mut slots, None, 200, &waker), Some(2));
+
+        assert_eq!(wake_expired(&mut slots, 50), Some(100));
+        assert!(slots.iter().take(3).all(|slot| slot.waker.is_some()));
+
+        assert_eq!(wake_expired(&mut slots, 100), Some(200));
+        assert!(slots[1].waker.is_none());
+        assert!(slots[0].waker.is_some() && slots[2].waker.is_some());
+
+        assert_eq!(wake_expired(&mut slots, 1000), None);
+        assert!(slots.iter().all(|slot| slot.waker.is_none()));
+    }
+
+    #[test]
+    fn test_enqueue_slot_reuse() {
+        let waker = noop_waker();
+        let mut slots = [EMPTY_SLOT; QUEUE_SIZE];
+        for i in 0..QUEUE_SIZE {
+            assert_eq!(enqueue(&mut slots, None, i as u64, &waker), Some(i));
+        }
+        assert_eq!(enqueue(&mut slots, None, 0, &waker), None);
+
+        // A future that is polled again keeps its slot, with the new deadline.
+        assert_eq!(enqueue(&mut slots, Some(5), 500, &waker), Some(5));
+        assert_eq!(slots[5].deadline, 500);
+
+        dequeue(&mut slots, 3);
+        assert!(!slots[3].used && slots[3].waker.is_none());
+        assert_eq!(wake_expired(&mut slots, 2), Some(4));
+        assert_eq!(enqueue(&mut slots, None, 1000, &waker), Some(3));
+        assert_eq!(enqueue(&mut slots, None, 0, &waker), None);
+    }
+}
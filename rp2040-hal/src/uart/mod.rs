@@ -37,9 +37,11 @@ mod reader;
 mod utils;
 mod writer;
 
+#[cfg(feature = "async")]
+pub use self::peripheral::handle_async_interrupt;
 pub use self::peripheral::UartPeripheral;
 pub use self::pins::*;
-pub use self::reader::{ReadError, ReadErrorType, Reader};
+pub use self::reader::{ReadError, ReadErrorType, Reader, RingReader};
 pub use self::utils::*;
 pub use self::writer::Writer;
 
@@ -16,6 +16,9 @@ use rp2040_pac::{UART0, UART1};
 
 #[cfg(feature = "eh1_0_alpha")]
 use eh1_0_alpha::serial as eh1;
+
+#[cfg(feature = "async")]
+use crate::async_utils::{wait_until, WakerCell};
 use pac::Peripherals;
 
 /// An UART Peripheral based on an underlying UART device.
@@ -189,6 +192,78 @@ impl<D: UartDevice, P: ValidUartPinout<D>> UartPeripheral<Enabled, D, P> {
     }
 }
 
+#[cfg(feature = "async")]
+static WAKERS: [WakerCell; 2] = [WakerCell::new(), WakerCell::new()];
+
+/// Wake the task waiting in [`UartPeripheral::read_async`] or [`UartPeripheral::write_async`].
+///
+/// Call this from the UARTx IRQ handler of the device `D`, e.g.
+/// `handle_async_interrupt::<pac::UART0>()`. The receive and transmit interrupts are disabled
+/// until the next time the task has to wait.
+#[cfg(feature = "async")]
+pub fn handle_async_interrupt<D: UartDevice>() {
+    // Safety: Only the interrupt mask is modified, which the waiting task sets up again.
+    let rb = unsafe {
+        &*if D::ID == 0 {
+            UART0::ptr()
+        } else {
+            UART1::ptr()
+        }
+    };
+    super::reader::disable_rx_interrupt(rb);
+    super::writer::disable_tx_interrupt(rb);
+    WAKERS[D::ID].wake();
+}
+
+#[cfg(feature = "async")]
+impl<D: UartDevice, P: ValidUartPinout<D>> UartPeripheral<Enabled, D, P> {
+    /// Reads bytes from the UART.
+    /// This function waits for the receive interrupt until the full buffer has been received.
+    ///
+    /// [`handle_async_interrupt`] must be called from the UARTx IRQ handler.
+    pub async fn read_async(&mut self, buffer: &mut [u8]) -> Result<(), ReadErrorType> {
+        let mut offset = 0;
+        while offset != buffer.len() {
+            match super::reader::read_raw(&self.device, &mut buffer[offset..]) {
+                Ok(bytes_read) => offset += bytes_read,
+                Err(Other(inner)) => return Err(inner.err_type),
+                Err(WouldBlock) => {
+                    let device = &self.device;
+                    wait_until(
+                        &WAKERS[D::ID],
+                        || super::reader::is_readable(device),
+                        || super::reader::enable_rx_interrupt(device),
+                    )
+                    .await
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes bytes to the UART.
+    /// This function waits for the transmit interrupt until the full buffer has been sent.
+    ///
+    /// [`handle_async_interrupt`] must be called from the UARTx IRQ handler.
+    pub async fn write_async(&mut self, data: &[u8]) {
+        let mut remaining = data;
+        while !remaining.is_empty() {
+            match super::writer::write_raw(&self.device, remaining) {
+                Ok(rest) => remaining = rest,
+                Err(_) => {
+                    let device = &self.device;
+                    wait_until(
+                        &WAKERS[D::ID],
+                        || super::writer::uart_is_writable(device),
+                        || super::writer::enable_tx_interrupt(device),
+                    )
+                    .await
+                }
+            }
+        }
+    }
+}
+
 impl<P: ValidUartPinout<UART0>> UartPeripheral<Enabled, UART0, P> {
     /// Split this peripheral into a separate reader and writer.
     pub fn split(self) -> (Reader<UART0, P>, Writer<UART0, P>) {
@@ -3,6 +3,8 @@
 //! This module is for receiving data with a UART.
 
 use super::{UartDevice, ValidUartPinout};
+use crate::dma::{ring, EndlessReadTarget, ReadTarget, SingleChannel, WriteTarget};
+use core::sync::atomic::{compiler_fence, Ordering};
 use rp2040_pac::uart0::RegisterBlock;
 
 use embedded_hal::serial::Read;
@@ -195,6 +197,178 @@ impl<D: UartDevice, P: ValidUartPinout<D>> Reader<D, P> {
     pub fn disable_rx_interrupt(&mut self) {
         disable_rx_interrupt(&self.device)
     }
+
+    /// Starts receiving into the ring buffer `buf` using the DMA channel `ch`.
+    ///
+    /// The size of `buf` needs to be a power of two between 2 and 32768 bytes, and `buf` needs to
+    /// be aligned to its size, see [`dma::ring`](crate::dma::ring).
+    ///
+    /// As the DMA channel reads the raw data bytes, receive errors are not reported.
+    pub fn into_ring_reader<CH, BUF>(self, ch: CH, mut buf: BUF) -> RingReader<D, P, CH, BUF>
+    where
+        CH: SingleChannel,
+        BUF: WriteTarget<TransmittedWord = u8>,
+    {
+        let (base, len) = buf.tx_address_count();
+        let device: *const RegisterBlock = &*self.device;
+        let trans_count = ch.ch().ch_trans_count.as_ptr() as *const u32;
+        RingReader {
+            device,
+            trans_count,
+            transfer: ring::Config::new(ch, self, buf).start(),
+            base: base as *const u8,
+            len,
+            restarts: 0,
+            read_count: 0,
+        }
+    }
+}
+
+// Safety: The UARTDR register is a FIFO, so the address stays the same for the whole transfer.
+unsafe impl<D: UartDevice, P: ValidUartPinout<D>> ReadTarget for Reader<D, P> {
+    type ReceivedWord = u8;
+
+    fn rx_treq(&self) -> Option<u8> {
+        Some(D::RX_DREQ)
+    }
+
+    fn rx_address_count(&self) -> (u32, u32) {
+        (self.device.uartdr.as_ptr() as u32, u32::MAX)
+    }
+
+    fn rx_increment(&self) -> bool {
+        false
+    }
+}
+
+impl<D: UartDevice, P: ValidUartPinout<D>> EndlessReadTarget for Reader<D, P> {}
+
+/// A [`Reader`] which continuously receives into a ring buffer using DMA. Obtained by calling
+/// [`Reader::into_ring_reader()`].
+///
+/// Received bytes have to be fetched with [`read()`](RingReader::read) before the DMA channel
+/// wraps around and overwrites them. This is detected using the transfer count of the DMA
+/// channel, and reported as [`ReadErrorType::Overrun`].
+pub struct RingReader<D, P, CH, BUF>
+where
+    D: UartDevice,
+    P: ValidUartPinout<D>,
+    CH: SingleChannel,
+    BUF: WriteTarget<TransmittedWord = u8>,
+{
+    transfer: ring::Transfer<CH, Reader<D, P>, BUF>,
+    device: *const RegisterBlock,
+    /// `TRANS_COUNT` register of the DMA channel
+    trans_count: *const u32,
+    base: *const u8,
+    len: u32,
+    /// Number of times the DMA channel was restarted after running out of transfers
+    restarts: u32,
+    /// Total number of bytes read, wrapping around
+    read_count: u32,
+}
+
+impl<D, P, CH, BUF> RingReader<D, P, CH, BUF>
+where
+    D: UartDevice,
+    P: ValidUartPinout<D>,
+    CH: SingleChannel,
+    BUF: WriteTarget<TransmittedWord = u8>,
+{
+    /// Returns how many received bytes are waiting to be read.
+    ///
+    /// Returns [`ReadErrorType::Overrun`] if more bytes were received than fit in the ring
+    /// buffer since the last read.
+    pub fn available(&self) -> Result<usize, ReadErrorType> {
+        ring_available(self.write_count(), self.read_count, self.len)
+    }
+
+    /// Copies received bytes into `data`, returning how many bytes were copied.
+    ///
+    /// Returns `0` if no new data has arrived. If more bytes were received than fit in the ring
+    /// buffer since the last read, all unread bytes are discarded and [`ReadErrorType::Overrun`]
+    /// is returned.
+    pub fn read(&mut self, data: &mut [u8]) -> Result<usize, ReadErrorType> {
+        // The channel stops after `u32::MAX` transfers. Restarting it reloads the transfer count,
+        // which has to be accounted for in `write_count`.
+        if self.read_trans_count() == 0 {
+            self.transfer.rearm();
+            if self.read_trans_count() != 0 {
+                self.restarts = self.restarts.wrapping_add(1);
+            }
+        }
+        let write_count = self.write_count();
+        // Make sure the buffer is not read before the DMA position.
+        compiler_fence(Ordering::Acquire);
+
+        let available = match ring_available(write_count, self.read_count, self.len) {
+            Ok(available) => available,
+            Err(e) => {
+                self.read_count = write_count;
+                return Err(e);
+            }
+        };
+        let count = available.min(data.len());
+        for byte in data[..count].iter_mut() {
+            let index = (self.read_count % self.len) as usize;
+            // Safety: The DMA channel does not write to this byte until it wraps around.
+            *byte = unsafe { self.base.add(index).read_volatile() };
+            self.read_count = self.read_count.wrapping_add(1);
+        }
+        Ok(count)
+    }
+
+    /// Total number of bytes written by the DMA channel, wrapping around.
+    fn write_count(&self) -> u32 {
+        // Read the position first, so that it can not be ahead of the transfer count.
+        let position = self.transfer.position() as u32;
+        let transfers = (u32::MAX - self.read_trans_count()).wrapping_sub(self.restarts);
+        ring_write_count(transfers, position, self.len)
+    }
+
+    fn read_trans_count(&self) -> u32 {
+        // Safety: Reading the transfer count of the channel owned by the transfer has no side
+        // effects.
+        unsafe { self.trans_count.read_volatile() }
+    }
+
+    /// Enables the receive timeout interrupt.
+    ///
+    /// The relevant UARTx IRQ fires once no further data has arrived for 32 bit periods, which
+    /// signals the end of a packet. Use [`check_idle()`](RingReader::check_idle) in the interrupt
+    /// handler to acknowledge it and then fetch the partial packet with
+    /// [`read()`](RingReader::read).
+    pub fn enable_idle_interrupt(&mut self) {
+        self.device().uartimsc.modify(|_r, w| w.rtim().set_bit());
+    }
+
+    /// Disables the receive timeout interrupt.
+    pub fn disable_idle_interrupt(&mut self) {
+        self.device().uartimsc.modify(|_r, w| w.rtim().clear_bit());
+    }
+
+    /// Returns whether the receive timeout interrupt is pending and clears it.
+    pub fn check_idle(&mut self) -> bool {
+        let device = self.device();
+        if device.uartmis.read().rtmis().bit_is_set() {
+            device.uarticr.write(|w| w.rtic().set_bit());
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Stops receiving, returning the reader, the DMA channel and the buffer.
+    pub fn stop(self) -> (Reader<D, P>, CH, BUF) {
+        let (ch, reader, buf) = self.transfer.stop();
+        (reader, ch, buf)
+    }
+
+    fn device(&self) -> &RegisterBlock {
+        // Safety: The register block belongs to the reader owned by the transfer, and only the
+        // interrupt registers are accessed, which the DMA channel does not use.
+        unsafe { &*self.device }
+    }
 }
 
 impl<D: UartDevice, P: ValidUartPinout<D>> Read<u8> for Reader<D, P> {
@@ -232,3 +406,62 @@ impl<D: UartDevice, P: ValidUartPinout<D>> eh1::nb::Read<u8> for Reader<D, P> {
         }
     }
 }
+
+/// Total number of bytes written into a ring buffer of `len` bytes, wrapping around.
+///
+/// `transfers` is the number of transfers started by the DMA channel, which may be ahead of the
+/// write `position` in the buffer, as the transfer count is decremented before the write is done.
+fn ring_write_count(transfers: u32, position: u32, len: u32) -> u32 {
+    // `len` is a power of two, so the wrapping arithmetic stays aligned to the buffer.
+    transfers.wrapping_sub(transfers.wrapping_sub(position) % len)
+}
+
+/// Number of unread bytes in a ring buffer of `len` bytes, or `Overrun` if the writer lapped
+/// the reader.
+fn ring_available(write_count: u32, read_count: u32, len: u32) -> Result<usize, ReadErrorType> {
+    let available = write_count.wrapping_sub(read_count);
+    if available > len {
+        Err(ReadErrorType::Overrun)
+    } else {
+        Ok(available as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring_write_count() {
+        assert_eq!(ring_write_count(0, 0, 16), 0);
+        assert_eq!(ring_write_count(5, 5, 16), 5);
+        // A transfer started but not written yet
+        assert_eq!(ring_write_count(6, 5, 16), 5);
+        // Full laps are counted
+        assert_eq!(ring_write_count(37, 5, 16), 37);
+        assert_eq!(ring_write_count(38, 5, 16), 37);
+        // Wrapping around the counter
+        assert_eq!(ring_write_count(1, 15, 16), u32::MAX);
+        assert_eq!(ring_write_count(u32::MAX, 15, 16), u32::MAX);
+    }
+
+    #[test]
+    fn test_ring_available() {
+        assert!(matches!(ring_available(0, 0, 16), Ok(0)));
+        assert!(matches!(ring_available(10, 4, 16), Ok(6)));
+        // A full buffer is still intact
+        assert!(matches!(ring_available(20, 4, 16), Ok(16)));
+        // A full lap is not mistaken for an empty buffer
+        assert!(matches!(ring_available(21, 5, 16), Ok(16)));
+        assert!(matches!(
+            ring_available(22, 5, 16),
+            Err(ReadErrorType::Overrun)
+        ));
+        assert!(matches!(
+            ring_available(37, 5, 16),
+            Err(ReadErrorType::Overrun)
+        ));
+        // Wrapping around the counters
+        assert!(matches!(ring_available(3, u32::MAX - 2, 16), Ok(6)));
+    }
+}
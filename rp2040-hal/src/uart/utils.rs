@@ -13,10 +13,25 @@ pub enum Error {
 pub trait State {}
 
 /// Trait to handle both underlying devices (UART0 & UART1)
-pub trait UartDevice: Deref<Target = RegisterBlock> + SubsystemReset + 'static {}
+pub trait UartDevice: Deref<Target = RegisterBlock> + SubsystemReset + 'static {
+    /// Index of this device, 0 for UART0 and 1 for UART1
+    const ID: usize;
+    /// The DREQ value for the TX FIFO of this device
+    const TX_DREQ: u8;
+    /// The DREQ value for the RX FIFO of this device
+    const RX_DREQ: u8;
+}
 
-impl UartDevice for UART0 {}
-impl UartDevice for UART1 {}
+impl UartDevice for UART0 {
+    const ID: usize = 0;
+    const TX_DREQ: u8 = crate::dma::DREQ_UART0_TX;
+    const RX_DREQ: u8 = crate::dma::DREQ_UART0_RX;
+}
+impl UartDevice for UART1 {
+    const ID: usize = 1;
+    const TX_DREQ: u8 = crate::dma::DREQ_UART1_TX;
+    const RX_DREQ: u8 = crate::dma::DREQ_UART1_RX;
+}
 
 /// UART is enabled.
 pub struct Enabled;
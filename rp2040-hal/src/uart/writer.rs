@@ -3,6 +3,7 @@
 //! This module is for transmitting data with a UART.
 
 use super::{UartDevice, ValidUartPinout};
+use crate::dma::{single_buffer, EndlessWriteTarget, ReadTarget, SingleChannel, WriteTarget};
 use core::fmt;
 use core::{convert::Infallible, marker::PhantomData};
 use embedded_hal::serial::Write;
@@ -150,8 +151,40 @@ impl<D: UartDevice, P: ValidUartPinout<D>> Writer<D, P> {
     pub fn disable_tx_interrupt(&mut self) {
         disable_tx_interrupt(&self.device)
     }
+
+    /// Starts writing `buf` using the DMA channel `ch`.
+    ///
+    /// Call `wait()` on the returned transfer to get the channel, the buffer and the writer back.
+    /// The last bytes may still be in the TX FIFO at that point, use [`Write::flush`] to wait
+    /// until they have been sent.
+    pub fn write_dma<CH, BUF>(self, ch: CH, buf: BUF) -> single_buffer::Transfer<CH, BUF, Self>
+    where
+        CH: SingleChannel,
+        BUF: ReadTarget<ReceivedWord = u8>,
+    {
+        single_buffer::Config::new(ch, buf, self).start()
+    }
+}
+
+// Safety: The UARTDR register is a FIFO, so the address stays the same for the whole transfer.
+unsafe impl<D: UartDevice, P: ValidUartPinout<D>> WriteTarget for Writer<D, P> {
+    type TransmittedWord = u8;
+
+    fn tx_treq(&self) -> Option<u8> {
+        Some(D::TX_DREQ)
+    }
+
+    fn tx_address_count(&mut self) -> (u32, u32) {
+        (self.device.uartdr.as_ptr() as u32, u32::MAX)
+    }
+
+    fn tx_increment(&self) -> bool {
+        false
+    }
 }
 
+impl<D: UartDevice, P: ValidUartPinout<D>> EndlessWriteTarget for Writer<D, P> {}
+
 impl<D: UartDevice, P: ValidUartPinout<D>> Write<u8> for Writer<D, P> {
     type Error = Infallible;
 
@@ -27,11 +27,14 @@
 //!         pac.USBCTRL_DPRAM,
 //!         clocks.usb_clock,
 //!         true,
+//!         false,
 //!         &mut pac.RESETS,
 //!     ));
 //! // Use the usb_bus as usual.
 //! ```
 //!
+//! For host mode, see the [`host`] module.
+//!
 //! See [pico_usb_serial.rs](https://github.com/rp-rs/rp-hal/tree/main/boards/pico/examples/pico_usb_serial.rs) for more complete examples
 //!
 //!
@@ -54,10 +57,43 @@
 //!
 //! If the required timing cannot be met, using an maximum packet size of the endpoint 0 above 18bytes
 //! (e.g. `.max_packet_size_ep0(64)`) should avoid that issue.
+//!
+//! ## RP2040-E5
+//!
+//! On RP2040 B0 and B1 chips, the USB controller may not notice the end of a bus reset when it
+//! is attached to a hub which broadcasts packets to other devices, so that the device fails to
+//! enumerate until it is replugged. The workaround documented in the datasheet can be enabled
+//! with the `rp2040_e5_workaround` argument of [`UsbBus::new`].
+//!
+//! It temporarily takes over GPIO15 to force the line state seen by the controller, from the end
+//! of the bus reset until the controller reports a connection. The GPIO configuration is restored
+//! afterwards, but the pin must not be used as the USB debug function (function 8).
+//!
+//! The workaround is started by the bus reset and advanced by `poll`, without blocking. The end
+//! of the bus reset raises no interrupt, so until it is seen, `poll` pends `USBCTRL_IRQ` to be
+//! called again. Once the J state is forced, the controller raises an interrupt when it notices
+//! the connection. Applications polling from `USBCTRL_IRQ` therefore need no extra setup, while
+//! the interrupt keeps firing for the duration of the bus reset, typically 10 to 20ms.
+//!
+//! ## Suspend and remote wakeup
+//!
+//! When the host suspends the bus, `poll` reports [`PollResult::Suspend`] and the device state
+//! changes to `UsbDeviceState::Suspend`. Once the host resumes the bus, [`PollResult::Resume`] is
+//! reported. While suspended, the device may wake up the host using
+//! [`UsbBus::remote_wakeup`], if the host enabled this feature:
+//!
+//! ```ignore
+//! if usb_dev.state() == UsbDeviceState::Suspend && usb_dev.remote_wakeup_enabled() {
+//!     usb_dev.bus().remote_wakeup();
+//! }
+//! ```
+
+pub mod host;
 
 use core::cell::RefCell;
 
 use crate::clocks::UsbClock;
+use crate::gpio::{InputOverride, OutputEnableOverride};
 use crate::pac::RESETS;
 use crate::pac::USBCTRL_DPRAM;
 use crate::pac::USBCTRL_REGS;
@@ -71,42 +107,149 @@ use usb_device::{
     Result as UsbResult, UsbDirection, UsbError,
 };
 
+/// Offset of the first endpoint data buffer in the DPRAM
+const DPRAM_BUFFERS_OFFSET: u16 = 0x180;
+/// Size of the DPRAM
+const DPRAM_SIZE: u16 = 4096;
+
+/// Buffer control: the buffer contains data
+const BUF_CTRL_FULL: u32 = 1 << 15;
+/// Buffer control: last buffer of the transfer
+const BUF_CTRL_LAST: u32 = 1 << 14;
+/// Buffer control: DATA1 PID
+const BUF_CTRL_DATA1: u32 = 1 << 13;
+/// Buffer control: reset the buffer selector to buffer 0
+const BUF_CTRL_RESET: u32 = 1 << 12;
+/// Buffer control: the buffer can be used by the controller
+const BUF_CTRL_AVAILABLE: u32 = 1 << 10;
+/// Buffer control: mask of the length field
+const BUF_CTRL_LENGTH_MASK: u32 = 0x3ff;
+/// Buffer control: position of the offset of buffer 1 of isochronous endpoints
+const BUF_CTRL_ISO_OFFSET_SHIFT: u32 = 27;
+
+/// GPIO used to force the DP line state for the RP2040-E5 workaround
+const ERRATA5_DP_GPIO: usize = 15;
+/// Function select of the USB debug muxing, which routes the GPIO input to DP
+const ERRATA5_USB_MUXING_FUNCSEL: u8 = 8;
+
 fn ep_addr_to_ep_buf_ctrl_idx(ep_addr: EndpointAddress) -> usize {
     ep_addr.index() * 2 + (if ep_addr.is_in() { 0 } else { 1 })
 }
+/// Distance in bytes between the two buffers of a double buffered isochronous endpoint, and the
+/// value of the buffer control field selecting it.
+fn iso_buffer1_offset(max_packet_size: u16) -> (u16, u32) {
+    match max_packet_size {
+        0..=128 => (128, 0),
+        129..=256 => (256, 1),
+        257..=512 => (512, 2),
+        _ => (1024, 3),
+    }
+}
+
+/// Size in 64 byte units of the DPRAM needed by an endpoint.
+fn ep_buffer_units(ep_type: EndpointType, max_packet_size: u16, double_buffered: bool) -> u16 {
+    // NOTE: the compiler is smart enough to recognize /64 as a 6bit right shift so let's
+    // keep the division here for the sake of clarity
+    let aligned_size = (max_packet_size + 63) / 64;
+    if !double_buffered {
+        aligned_size
+    } else if ep_type == EndpointType::Isochronous {
+        iso_buffer1_offset(max_packet_size).0 / 64 + aligned_size
+    } else {
+        // Buffer 1 of other endpoints is always located 64 bytes after buffer 0
+        1 + aligned_size
+    }
+}
+
 #[derive(Debug)]
 struct Endpoint {
     ep_type: EndpointType,
     max_packet_size: u16,
     buffer_offset: u16,
+    double_buffered: bool,
+    /// Buffer to be used next by `ep_read` or `ep_write` if the endpoint is double buffered
+    next_buffer: usize,
+    /// Whether DATA1 is used the next time a buffer is armed, if the endpoint is double buffered
+    next_pid: bool,
 }
 impl Endpoint {
-    unsafe fn get_buf_parts(&self) -> (*mut u8, usize) {
+    /// Buffer control bits of a double buffered endpoint's next data PID, toggling it.
+    fn take_pid(&mut self) -> u32 {
+        // Isochronous transfers always use DATA0
+        if self.ep_type == EndpointType::Isochronous {
+            return 0;
+        }
+        let pid = if self.next_pid { BUF_CTRL_DATA1 } else { 0 };
+        self.next_pid = !self.next_pid;
+        pid
+    }
+
+    unsafe fn get_buf_parts(&self, buffer: usize) -> (*mut u8, usize) {
         const DPRAM_BASE: *mut u8 = USBCTRL_DPRAM::ptr() as *mut u8;
         if self.ep_type == EndpointType::Control {
             (DPRAM_BASE.offset(0x100), self.max_packet_size as usize)
         } else {
+            let buffer1_offset = if self.ep_type == EndpointType::Isochronous {
+                iso_buffer1_offset(self.max_packet_size).0
+            } else {
+                64
+            };
+            let offset =
+                DPRAM_BUFFERS_OFFSET + self.buffer_offset * 64 + buffer as u16 * buffer1_offset;
             (
-                DPRAM_BASE.offset(0x180 + (self.buffer_offset * 64) as isize),
+                DPRAM_BASE.offset(offset as isize),
                 self.max_packet_size as usize,
             )
         }
     }
 
-    fn get_buf(&self) -> &'static [u8] {
+    /// Value of one half of a double buffered endpoint's buffer control register.
+    ///
+    /// Half 1 also covers the buffer offset field of isochronous endpoints, which would be
+    /// cleared otherwise.
+    fn buf_control_half(&self, buffer: usize, value: u32) -> u16 {
+        let mut value = value;
+        if buffer == 1 && self.ep_type == EndpointType::Isochronous {
+            value |= iso_buffer1_offset(self.max_packet_size).1 << (BUF_CTRL_ISO_OFFSET_SHIFT - 16);
+        }
+        value as u16
+    }
+
+    fn get_buf(&self, buffer: usize) -> &'static [u8] {
         unsafe {
-            let (base, len) = self.get_buf_parts();
+            let (base, len) = self.get_buf_parts(buffer);
             core::slice::from_raw_parts(base as *const _, len)
         }
     }
-    fn get_buf_mut(&self) -> &'static mut [u8] {
+    fn get_buf_mut(&self, buffer: usize) -> &'static mut [u8] {
         unsafe {
-            let (base, len) = self.get_buf_parts();
+            let (base, len) = self.get_buf_parts(buffer);
             core::slice::from_raw_parts_mut(base, len)
         }
     }
 }
 
+/// Write one half of a double buffered endpoint's buffer control register.
+///
+/// The controller updates the other half on its own, so a 16 bit write is used instead of a
+/// read-modify-write of the whole register.
+fn write_buf_control_half(buf_control: *mut u32, buffer: usize, value: u16) {
+    // Safety: The DPRAM supports 16 bit accesses, and buffer is either 0 or 1.
+    unsafe { core::ptr::write_volatile((buf_control as *mut u16).add(buffer), value) };
+}
+
+/// Progress of the RP2040-E5 workaround
+#[derive(Clone, Copy)]
+enum Errata5State {
+    /// Not running
+    Idle,
+    /// Waiting for the end of the bus reset
+    WaitEndOfReset,
+    /// Forcing the J line state until the controller is connected, with the GPIO15
+    /// configuration to restore afterwards
+    ForceLineStateJ { gpio_ctrl_prev: u32, pad_prev: u32 },
+}
+
 struct Inner {
     ctrl_reg: USBCTRL_REGS,
     ctrl_dpram: USBCTRL_DPRAM,
@@ -114,9 +257,14 @@ struct Inner {
     out_endpoints: [Option<Endpoint>; 16],
     next_offset: u16,
     read_setup: bool,
+    errata5: bool,
+    errata5_state: Errata5State,
+    bulk_double_buffering: bool,
+    /// OUT endpoints with a filled buffer whose `BUFF_STATUS` bit was already cleared
+    out_pending: u16,
 }
 impl Inner {
-    fn new(ctrl_reg: USBCTRL_REGS, ctrl_dpram: USBCTRL_DPRAM) -> Self {
+    fn new(ctrl_reg: USBCTRL_REGS, ctrl_dpram: USBCTRL_DPRAM, errata5: bool) -> Self {
         Self {
             ctrl_reg,
             ctrl_dpram,
@@ -124,9 +272,111 @@ impl Inner {
             out_endpoints: Default::default(),
             next_offset: 0,
             read_setup: false,
+            errata5,
+            errata5_state: Errata5State::Idle,
+            bulk_double_buffering: false,
+            out_pending: 0,
         }
     }
 
+    /// RP2040-E5 workaround: advance the workaround started by a bus reset, without waiting.
+    ///
+    /// Once the bus reset ended, i.e. the line state is no longer SE0, the J line state is forced
+    /// through GPIO15 until the controller considers itself connected.
+    fn errata5_poll(&mut self) {
+        match self.errata5_state {
+            Errata5State::Idle => {}
+            Errata5State::WaitEndOfReset => {
+                if self.ctrl_reg.sie_status.read().line_state().bits() != 0 {
+                    self.errata5_state = self.errata5_force_line_state_j();
+                }
+            }
+            Errata5State::ForceLineStateJ { .. } => {
+                if self.ctrl_reg.sie_status.read().connected().bit_is_set() {
+                    self.errata5_restore();
+                }
+            }
+        }
+    }
+
+    fn errata5_force_line_state_j(&mut self) -> Errata5State {
+        // Safety: The GPIO and pad configuration is restored by `errata5_restore`.
+        let io_bank0 = unsafe { &(*pac::IO_BANK0::ptr()) };
+        let pads = unsafe { &(*pac::PADS_BANK0::ptr()) };
+        let gpio_ctrl = &io_bank0.gpio[ERRATA5_DP_GPIO].gpio_ctrl;
+        let pad = &pads.gpio[ERRATA5_DP_GPIO];
+        let (gpio_ctrl_prev, pad_prev) = (gpio_ctrl.read().bits(), pad.read().bits());
+
+        // Enable bus keep and force the pin to tristate, so that the USB DP muxing doesn't
+        // affect the pin state.
+        pad.modify(|_, w| w.pue().set_bit().pde().set_bit());
+        gpio_ctrl.modify(|_, w| unsafe {
+            w.oeover().bits(OutputEnableOverride::Disable as u8);
+            w.funcsel().bits(ERRATA5_USB_MUXING_FUNCSEL);
+            // The J state is DP = 1 and DM = 0 for a full speed device. DM is gated off already.
+            w.inover().bits(InputOverride::AlwaysHigh as u8)
+        });
+
+        // Keep the DP pull up enabled while switching away from the PHY.
+        self.ctrl_reg
+            .usbphy_direct
+            .modify(|_, w| w.dp_pullup_en().set_bit());
+        self.ctrl_reg
+            .usbphy_direct_override
+            .modify(|_, w| w.dp_pullup_en_override_en().set_bit());
+
+        // Raise an interrupt once the controller notices the connection, so that `poll` is
+        // called to finish the workaround.
+        self.ctrl_reg.sie_status.write(|w| w.connected().set_bit());
+        self.ctrl_reg.inte.modify(|_, w| w.dev_conn_dis().set_bit());
+
+        // Switch to the GPIO with the J state forced.
+        self.ctrl_reg.usb_muxing.write(|w| {
+            w.to_digital_pad().set_bit();
+            w.softcon().set_bit()
+        });
+
+        Errata5State::ForceLineStateJ {
+            gpio_ctrl_prev,
+            pad_prev,
+        }
+    }
+
+    /// Switch back to the PHY and restore the GPIO, if the J line state is currently forced.
+    fn errata5_restore(&mut self) {
+        let (gpio_ctrl_prev, pad_prev) = match self.errata5_state {
+            Errata5State::ForceLineStateJ {
+                gpio_ctrl_prev,
+                pad_prev,
+            } => (gpio_ctrl_prev, pad_prev),
+            _ => {
+                self.errata5_state = Errata5State::Idle;
+                return;
+            }
+        };
+        self.errata5_state = Errata5State::Idle;
+
+        self.ctrl_reg.usb_muxing.write(|w| {
+            w.to_phy().set_bit();
+            w.softcon().set_bit()
+        });
+        self.ctrl_reg
+            .usbphy_direct_override
+            .modify(|_, w| w.dp_pullup_en_override_en().clear_bit());
+        self.ctrl_reg
+            .inte
+            .modify(|_, w| w.dev_conn_dis().clear_bit());
+        self.ctrl_reg.sie_status.write(|w| w.connected().set_bit());
+
+        // Safety: Restores the configuration saved by `errata5_force_line_state_j`.
+        let io_bank0 = unsafe { &(*pac::IO_BANK0::ptr()) };
+        let pads = unsafe { &(*pac::PADS_BANK0::ptr()) };
+        io_bank0.gpio[ERRATA5_DP_GPIO]
+            .gpio_ctrl
+            .write(|w| unsafe { w.bits(gpio_ctrl_prev) });
+        pads.gpio[ERRATA5_DP_GPIO].write(|w| unsafe { w.bits(pad_prev) });
+    }
+
     fn ep_allocate(
         &mut self,
         ep_addr: Option<EndpointAddress>,
@@ -184,23 +434,32 @@ impl Inner {
                 ep_type,
                 max_packet_size,
                 buffer_offset: 0, // not used on CTRL ep
+                double_buffered: false,
+                next_buffer: 0,
+                next_pid: false,
             });
         } else {
+            // Isochronous endpoints are double buffered, so that one buffer can be prepared
+            // while the other one is transferred in the current frame.
+            let double_buffered = ep_type == EndpointType::Isochronous
+                || (ep_type == EndpointType::Bulk && self.bulk_double_buffering);
+
             // size in 64bytes units.
-            // NOTE: the compiler is smart enough to recognize /64 as a 6bit right shift so let's
-            // keep the division here for the sake of clarity
-            let aligned_sized = (max_packet_size + 63) / 64;
-            if (self.next_offset + aligned_sized) > (4096 / 64) {
+            let units = ep_buffer_units(ep_type, max_packet_size, double_buffered);
+            if (self.next_offset + units) > (DPRAM_SIZE - DPRAM_BUFFERS_OFFSET) / 64 {
                 return Err(UsbError::EndpointMemoryOverflow);
             }
 
             let buffer_offset = self.next_offset;
-            self.next_offset += aligned_sized;
+            self.next_offset += units;
 
             *maybe_ep = Some(Endpoint {
                 ep_type,
                 max_packet_size,
                 buffer_offset,
+                double_buffered,
+                next_buffer: 0,
+                next_pid: false,
             });
         }
         Ok(ep_addr)
@@ -218,11 +477,11 @@ impl Inner {
         });
 
         for (index, ep) in itertools::interleave(
-            self.in_endpoints.iter().skip(1),  // skip control endpoint
-            self.out_endpoints.iter().skip(1), // skip control endpoint
+            self.in_endpoints.iter_mut().skip(1),  // skip control endpoint
+            self.out_endpoints.iter_mut().skip(1), // skip control endpoint
         )
         .enumerate()
-        .filter_map(|(i, ep)| ep.as_ref().map(|ep| (i, ep)))
+        .filter_map(|(i, ep)| ep.as_mut().map(|ep| (i, ep)))
         {
             use pac::usbctrl_dpram::ep_control::ENDPOINT_TYPE_A;
             let ep_type = match ep.ep_type {
@@ -236,12 +495,29 @@ impl Inner {
             self.ctrl_dpram.ep_control[index].modify(|_, w| unsafe {
                 w.endpoint_type().variant(ep_type);
                 w.interrupt_per_buff().set_bit();
+                w.double_buffered().bit(ep.double_buffered);
                 w.enable().set_bit();
-                w.buffer_address().bits(0x180 + (ep.buffer_offset << 6))
+                w.buffer_address()
+                    .bits(DPRAM_BUFFERS_OFFSET + (ep.buffer_offset << 6))
             });
             // reset OUT ep and prepare IN ep to accept data
             let buf_control = &self.ctrl_dpram.ep_buffer_control[index + 2];
-            if (index & 1) == 0 {
+            if ep.double_buffered {
+                ep.next_buffer = 0;
+                // first transfer occurs on DATA0
+                ep.next_pid = false;
+                let mut value = BUF_CTRL_RESET;
+                if ep.ep_type == EndpointType::Isochronous {
+                    value |= iso_buffer1_offset(ep.max_packet_size).1 << BUF_CTRL_ISO_OFFSET_SHIFT;
+                }
+                if (index & 1) != 0 {
+                    // arm both buffers of OUT eps
+                    let half = BUF_CTRL_AVAILABLE | u32::from(ep.max_packet_size);
+                    value |= half | ep.take_pid();
+                    value |= (half | ep.take_pid()) << 16;
+                }
+                buf_control.write(|w| unsafe { w.bits(value) });
+            } else if (index & 1) == 0 {
                 // first write occur on DATA0 so prepare the pid bit to be flipped
                 buf_control.write(|w| w.pid_0().set_bit());
             } else {
@@ -263,11 +539,30 @@ impl Inner {
             .ok_or(UsbError::InvalidEndpoint)?;
 
         let buf_control = &self.ctrl_dpram.ep_buffer_control[index * 2];
+        if ep.double_buffered {
+            let buffer = ep.next_buffer;
+            let half = buf_control.read().bits() >> (16 * buffer);
+            if half & BUF_CTRL_AVAILABLE != 0 {
+                return Err(UsbError::WouldBlock);
+            }
+
+            let ep_buf = ep.get_buf_mut(buffer);
+            if ep_buf.len() < buf.len() {
+                return Err(UsbError::BufferOverflow);
+            }
+            ep_buf[..buf.len()].copy_from_slice(buf);
+
+            let half = BUF_CTRL_AVAILABLE | BUF_CTRL_FULL | buf.len() as u32 | ep.take_pid();
+            let half = ep.buf_control_half(buffer, half);
+            write_buf_control_half(buf_control.as_ptr(), buffer, half);
+            ep.next_buffer ^= 1;
+            return Ok(buf.len());
+        }
         if buf_control.read().available_0().bit_is_set() {
             return Err(UsbError::WouldBlock);
         }
 
-        let ep_buf = ep.get_buf_mut();
+        let ep_buf = ep.get_buf_mut(0);
         if ep_buf.len() < buf.len() {
             return Err(UsbError::BufferOverflow);
         }
@@ -292,6 +587,38 @@ impl Inner {
             .ok_or(UsbError::InvalidEndpoint)?;
 
         let buf_control = &self.ctrl_dpram.ep_buffer_control[index * 2 + 1];
+        if ep.double_buffered {
+            let buffer = ep.next_buffer;
+            let half = buf_control.read().bits() >> (16 * buffer);
+            if half & BUF_CTRL_FULL == 0 {
+                return Err(UsbError::WouldBlock);
+            }
+            let len = (half & BUF_CTRL_LENGTH_MASK) as usize;
+            if len > buf.len() {
+                return Err(UsbError::BufferOverflow);
+            }
+            buf[..len].copy_from_slice(&ep.get_buf(buffer)[..len]);
+
+            let half = BUF_CTRL_AVAILABLE | u32::from(ep.max_packet_size) | ep.take_pid();
+            let half = ep.buf_control_half(buffer, half);
+            write_buf_control_half(buf_control.as_ptr(), buffer, half);
+            ep.next_buffer ^= 1;
+
+            // Clear the OUT flag first, then check the other buffer. If the controller filled it
+            // before the flag was cleared, it is reported by the next `poll()` instead, and the
+            // interrupt is raised again for it.
+            self.ctrl_reg
+                .buff_status
+                .write(|w| unsafe { w.bits(1 << (index * 2 + 1)) });
+            let other = buf_control.read().bits() >> (16 * ep.next_buffer);
+            if other & BUF_CTRL_FULL != 0 {
+                self.out_pending |= 1 << index;
+                pac::NVIC::pend(pac::Interrupt::USBCTRL_IRQ);
+            } else {
+                self.out_pending &= !(1 << index);
+            }
+            return Ok(len);
+        }
         let buf_control_val = buf_control.read();
 
         let process_setup = index == 0 && self.read_setup;
@@ -312,7 +639,7 @@ impl Inner {
                 return Err(UsbError::WouldBlock);
             }
             let len: usize = buf_control_val.length_0().bits().into();
-            (ep.get_buf(), len)
+            (ep.get_buf(0), len)
         };
 
         if len > buf.len() {
@@ -361,11 +688,18 @@ pub struct UsbBus {
 
 impl UsbBus {
     /// Create new usb bus struct and bring up usb as device.
+    ///
+    /// If `rp2040_e5_workaround` is set, the workaround for the RP2040-E5 errata is applied on
+    /// every bus reset when running on an affected chip, see the [module level
+    /// documentation](self#rp2040-e5). It is advanced by `poll`, which must be called from
+    /// `USBCTRL_IRQ` or a busy loop, as `poll` keeps that interrupt pending until the end of the
+    /// bus reset.
     pub fn new(
         ctrl_reg: USBCTRL_REGS,
         ctrl_dpram: USBCTRL_DPRAM,
         _pll: UsbClock,
         force_vbus_detect_bit: bool,
+        rp2040_e5_workaround: bool,
         resets: &mut RESETS,
     ) -> Self {
         ctrl_reg.reset_bring_down(resets);
@@ -398,10 +732,67 @@ impl UsbBus {
             w.controller_en().set_bit()
         });
 
+        // The errata is fixed from the B2 revision onwards.
+        // Safety: it is always safe to read this read-only register
+        let revision = unsafe { (*pac::SYSINFO::ptr()).chip_id.read().revision().bits() };
+        let errata5 = rp2040_e5_workaround && revision < 2;
+
         Self {
-            inner: Mutex::new(RefCell::new(Inner::new(ctrl_reg, ctrl_dpram))),
+            inner: Mutex::new(RefCell::new(Inner::new(ctrl_reg, ctrl_dpram, errata5))),
         }
     }
+
+    /// Enable or disable double buffering of bulk endpoints.
+    ///
+    /// Double buffering lets the controller transfer one packet while the next one is prepared,
+    /// which increases the throughput of bulk transfers, at the cost of twice the DPRAM per
+    /// endpoint. This only affects endpoints allocated afterwards, so it has to be called before
+    /// the `UsbBus` is passed to a `UsbBusAllocator`:
+    ///
+    /// ```ignore
+    /// let mut usb_bus = UsbBus::new(/* ... */);
+    /// usb_bus.set_bulk_double_buffering(true);
+    /// let usb_bus = UsbBusAllocator::new(usb_bus);
+    /// ```
+    pub fn set_bulk_double_buffering(&mut self, enable: bool) {
+        interrupt::free(|cs| {
+            let mut inner = self.inner.borrow(cs).borrow_mut();
+            inner.bulk_double_buffering = enable;
+        })
+    }
+
+    /// Number of the current frame, taken from the last start of frame packet.
+    ///
+    /// Reading the frame number also clears the start of frame interrupt.
+    pub fn frame_number(&self) -> u16 {
+        interrupt::free(|cs| {
+            let inner = self.inner.borrow(cs).borrow_mut();
+            inner.ctrl_reg.sof_rd.read().count().bits()
+        })
+    }
+
+    /// Enable or disable the interrupt raised for each start of frame packet, e.g. to
+    /// synchronise isochronous streams to the host.
+    ///
+    /// The interrupt is cleared by reading the frame number using
+    /// [`frame_number`](Self::frame_number).
+    pub fn set_sof_interrupt(&self, enable: bool) {
+        interrupt::free(|cs| {
+            let inner = self.inner.borrow(cs).borrow_mut();
+            inner.ctrl_reg.inte.modify(|_, w| w.dev_sof().bit(enable));
+        })
+    }
+
+    /// Signal a remote wakeup to the host.
+    ///
+    /// This must only be used while the bus is suspended, and if the host enabled remote wakeup
+    /// (see `UsbDevice::remote_wakeup_enabled`).
+    pub fn remote_wakeup(&self) {
+        interrupt::free(|cs| {
+            let inner = self.inner.borrow(cs).borrow_mut();
+            inner.ctrl_reg.sie_ctrl.modify(|_, w| w.resume().set_bit());
+        })
+    }
 }
 
 impl UsbBusTrait for UsbBus {
@@ -427,7 +818,7 @@ impl UsbBusTrait for UsbBus {
             // TODO: is it worth having a debug_assert for that here?
 
             // Enable interrupt generation when a buffer is done, when the bus is reset,
-            // and when a setup packet is received
+            // when a setup packet is received, and when the bus is suspended or resumed
             // this should be sufficient for device mode, will need more for host.
             inner.ctrl_reg.inte.modify(|_, w| {
                 w.buff_status()
@@ -436,6 +827,10 @@ impl UsbBusTrait for UsbBus {
                     .set_bit()
                     .setup_req()
                     .set_bit()
+                    .dev_suspend()
+                    .set_bit()
+                    .dev_resume_from_host()
+                    .set_bit()
             });
 
             // enable pull up to let the host know we exist.
@@ -458,11 +853,16 @@ impl UsbBusTrait for UsbBus {
 
             // reset all endpoints
             inner.ep_reset_all();
+            inner.out_pending = 0;
 
             // Reset address register
             inner.ctrl_reg.addr_endp.reset();
-            // TODO: RP2040-E5: work around implementation
-            // TODO: reset all endpoints & buffer statuses
+
+            if inner.errata5 {
+                // A bus reset may interrupt a running workaround, start over.
+                inner.errata5_restore();
+                inner.errata5_state = Errata5State::WaitEndOfReset;
+            }
         })
     }
     fn set_device_address(&self, addr: u8) {
@@ -518,16 +918,25 @@ impl UsbBusTrait for UsbBus {
         })
     }
     fn suspend(&self) {
-        todo!()
+        // Nothing to do: the controller keeps monitoring the bus for a resume or reset
     }
     fn resume(&self) {
-        todo!()
+        // Nothing to do: the controller resumes operation on its own
     }
     fn poll(&self) -> PollResult {
+        let errata5_waiting = interrupt::free(|cs| {
+            let mut inner = self.inner.borrow(cs).borrow_mut();
+            inner.errata5_poll();
+            matches!(inner.errata5_state, Errata5State::WaitEndOfReset)
+        });
+        if errata5_waiting {
+            // The end of the bus reset raises no interrupt, so keep the USB interrupt pending
+            // until it is seen.
+            pac::NVIC::pend(pac::Interrupt::USBCTRL_IRQ);
+        }
+
         interrupt::free(|cs| {
             let mut inner = self.inner.borrow(cs).borrow_mut();
-            // TODO: check for suspend request
-            // TODO: check for resume request
 
             // check for bus reset
             let sie_status = inner.ctrl_reg.sie_status.read();
@@ -535,7 +944,20 @@ impl UsbBusTrait for UsbBus {
                 return PollResult::Reset;
             }
 
-            let (mut ep_out, mut ep_in_complete, mut ep_setup): (u16, u16, u16) = (0, 0, 0);
+            // check for suspend request
+            if sie_status.suspended().bit_is_set() {
+                inner.ctrl_reg.sie_status.write(|w| w.suspended().set_bit());
+                return PollResult::Suspend;
+            }
+
+            // check for resume request
+            if sie_status.resume().bit_is_set() {
+                inner.ctrl_reg.sie_status.write(|w| w.resume().set_bit());
+                return PollResult::Resume;
+            }
+
+            let (mut ep_out, mut ep_in_complete, mut ep_setup): (u16, u16, u16) =
+                (core::mem::take(&mut inner.out_pending), 0, 0);
 
             let buff_status = inner.ctrl_reg.buff_status.read().bits();
             if buff_status != 0 {
@@ -579,3 +1001,39 @@ impl UsbBusTrait for UsbBus {
 
     const QUIRK_SET_ADDRESS_BEFORE_STATUS: bool = false;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buf_control_half_iso_offset() {
+        let ep = Endpoint {
+            ep_type: EndpointType::Isochronous,
+            max_packet_size: 512,
+            buffer_offset: 0,
+            double_buffered: true,
+            next_buffer: 1,
+            next_pid: false,
+        };
+        let value = BUF_CTRL_AVAILABLE | 512;
+        let half1 = u32::from(ep.buf_control_half(1, value)) << 16;
+        assert_eq!((half1 >> BUF_CTRL_ISO_OFFSET_SHIFT) & 0b11, 2);
+        assert_eq!(
+            half1 & 0xffff_0000 & !(0b11 << BUF_CTRL_ISO_OFFSET_SHIFT),
+            value << 16
+        );
+        // Half 0 does not cover the offset field
+        assert_eq!(u32::from(ep.buf_control_half(0, value)), value);
+
+        let bulk = Endpoint {
+            ep_type: EndpointType::Bulk,
+            max_packet_size: 64,
+            ..ep
+        };
+        assert_eq!(
+            u32::from(bulk.buf_control_half(1, BUF_CTRL_FULL | 64)),
+            BUF_CTRL_FULL | 64
+        );
+    }
+}
@@ -0,0 +1,799 @@
+//! USB host mode
+//!
+//! Drives the USB controller as a host for a single, directly connected device. Control and bulk
+//! transfers are performed synchronously on the controller's single non-interrupt endpoint
+//! (EPX), while up to 15 interrupt IN endpoints are polled by the hardware in the background.
+//!
+//! ```no_run
+//! use rp2040_hal::{clocks::init_clocks_and_plls, pac, usb::host::UsbHost, watchdog::Watchdog};
+//!
+//! const XOSC_CRYSTAL_FREQ: u32 = 12_000_000; // Typically found in BSP crates
+//!
+//! let mut pac = pac::Peripherals::take().unwrap();
+//! let core = pac::CorePeripherals::take().unwrap();
+//! let mut watchdog = Watchdog::new(pac.WATCHDOG);
+//! let mut clocks = init_clocks_and_plls(
+//!     XOSC_CRYSTAL_FREQ,
+//!     pac.XOSC,
+//!     pac.CLOCKS,
+//!     pac.PLL_SYS,
+//!     pac.PLL_USB,
+//!     &mut pac.RESETS,
+//!     &mut watchdog
+//! ).ok().unwrap();
+//! let mut delay = cortex_m::delay::Delay::new(core.SYST, 125_000_000);
+//!
+//! let mut host = UsbHost::new(
+//!     pac.USBCTRL_REGS,
+//!     pac.USBCTRL_DPRAM,
+//!     clocks.usb_clock,
+//!     &mut pac.RESETS,
+//! );
+//! while !host.is_connected() {}
+//! let descriptor = host.enumerate(&mut delay).unwrap();
+//! // A boot protocol keyboard, with its interrupt IN endpoint 1 taken from the configuration
+//! // descriptor.
+//! host.set_configuration(1).unwrap();
+//! let keyboard = host.open_interrupt_in(1, 8, 10).unwrap();
+//! let mut report = [0; 8];
+//! loop {
+//!     if let Ok(len) = host.read_interrupt(&keyboard, &mut report) {
+//!         // Handle the report
+//!     }
+//! }
+//! ```
+//!
+//! The controller keeps retrying transfers the device answers with NAK, so by default
+//! `control_in`, `bulk_in` and friends block until the device sends or accepts data. A limit can
+//! be set with [`UsbHost::set_nak_limit`], after which the transfer fails with [`Error::Nak`].
+//! Interrupt endpoints are still better suited to devices like keyboards or barcode scanners,
+//! which only send data when an event occurs.
+
+use core::ptr::{read_volatile, write_volatile};
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use super::{
+    BUF_CTRL_AVAILABLE, BUF_CTRL_DATA1, BUF_CTRL_FULL, BUF_CTRL_LAST, BUF_CTRL_LENGTH_MASK,
+};
+use crate::clocks::UsbClock;
+use crate::pac::RESETS;
+use crate::pac::USBCTRL_DPRAM;
+use crate::pac::USBCTRL_REGS;
+use crate::resets::SubsystemReset;
+
+/// Address assigned to the device during enumeration
+const DEVICE_ADDRESS: u8 = 1;
+
+/// Number of interrupt endpoints the controller can poll
+const INTERRUPT_ENDPOINTS: usize = 15;
+
+/// Offset of the EPX control register in the DPRAM
+const EPX_CTRL_OFFSET: usize = 0x100;
+/// Offset of the EPX buffer control register in the DPRAM
+const EPX_BUF_CTRL_OFFSET: usize = 0x80;
+/// Offset of the control register of the first interrupt endpoint in the DPRAM
+const INT_EP_CTRL_OFFSET: usize = 0x08;
+/// Offset of the buffer control register of the first interrupt endpoint in the DPRAM
+const INT_EP_BUF_CTRL_OFFSET: usize = 0x88;
+/// Offset of the data buffers in the DPRAM. EPX uses the first one.
+const DATA_OFFSET: usize = 0x180;
+/// Size of each data buffer
+const BUFFER_SIZE: usize = 64;
+
+/// Endpoint control: enable the endpoint
+const EP_CTRL_ENABLE: u32 = 1 << 31;
+/// Endpoint control: raise BUFF_STATUS for each completed buffer
+const EP_CTRL_INTERRUPT_PER_BUFF: u32 = 1 << 29;
+/// Endpoint control: position of the endpoint type
+const EP_CTRL_TYPE_SHIFT: u32 = 26;
+/// Endpoint control: position of the polling interval of interrupt endpoints
+const EP_CTRL_INTERVAL_SHIFT: u32 = 16;
+
+/// ADDR_ENDPx: position of the endpoint number
+const ADDR_ENDP_ENDPOINT_SHIFT: u32 = 16;
+
+/// Descriptor type of device descriptors
+const DESCRIPTOR_TYPE_DEVICE: u8 = 1;
+
+/// USB host errors
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// No device is connected
+    Disconnected,
+    /// The device answered with STALL
+    Stall,
+    /// The device did not answer
+    Timeout,
+    /// The device answered with an unexpected data PID
+    DataSequence,
+    /// The data does not fit in the buffer
+    BufferOverflow,
+    /// All interrupt endpoints are in use
+    NoFreeEndpoint,
+    /// The device answered with NAK more often than the limit set with
+    /// [`UsbHost::set_nak_limit`]
+    Nak,
+}
+
+/// Speed of the connected device
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Speed {
+    /// Low speed, 1.5Mbit/s
+    Low,
+    /// Full speed, 12Mbit/s
+    Full,
+}
+
+/// Endpoint types, as used in the endpoint control registers
+#[derive(Clone, Copy)]
+enum EndpointType {
+    Control = 0,
+    Bulk = 2,
+    Interrupt = 3,
+}
+
+/// Setup packet of a control transfer
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SetupPacket {
+    /// bmRequestType: direction, type and recipient of the request
+    pub request_type: u8,
+    /// bRequest
+    pub request: u8,
+    /// wValue
+    pub value: u16,
+    /// wIndex
+    pub index: u16,
+    /// wLength: number of bytes in the data stage
+    pub length: u16,
+}
+
+impl SetupPacket {
+    /// GET_DESCRIPTOR request for `length` bytes of the descriptor `descriptor_type`/`index`.
+    pub fn get_descriptor(descriptor_type: u8, index: u8, length: u16) -> Self {
+        Self {
+            request_type: 0x80,
+            request: 6,
+            value: (u16::from(descriptor_type) << 8) | u16::from(index),
+            index: 0,
+            length,
+        }
+    }
+
+    /// SET_ADDRESS request.
+    pub fn set_address(address: u8) -> Self {
+        Self {
+            request_type: 0x00,
+            request: 5,
+            value: address.into(),
+            index: 0,
+            length: 0,
+        }
+    }
+
+    /// SET_CONFIGURATION request.
+    pub fn set_configuration(value: u8) -> Self {
+        Self {
+            request_type: 0x00,
+            request: 9,
+            value: value.into(),
+            index: 0,
+            length: 0,
+        }
+    }
+
+    fn is_in(&self) -> bool {
+        self.request_type & 0x80 != 0
+    }
+
+    fn to_bytes(self) -> [u8; 8] {
+        let [value_lo, value_hi] = self.value.to_le_bytes();
+        let [index_lo, index_hi] = self.index.to_le_bytes();
+        let [length_lo, length_hi] = self.length.to_le_bytes();
+        [
+            self.request_type,
+            self.request,
+            value_lo,
+            value_hi,
+            index_lo,
+            index_hi,
+            length_lo,
+            length_hi,
+        ]
+    }
+}
+
+/// The parts of the device descriptor needed to identify a device
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DeviceDescriptor {
+    /// USB specification release number, in BCD
+    pub usb_release: u16,
+    /// Class code
+    pub device_class: u8,
+    /// Subclass code
+    pub device_subclass: u8,
+    /// Protocol code
+    pub device_protocol: u8,
+    /// Maximum packet size of endpoint 0
+    pub max_packet_size_0: u8,
+    /// Vendor ID
+    pub vendor_id: u16,
+    /// Product ID
+    pub product_id: u16,
+    /// Device release number, in BCD
+    pub device_release: u16,
+    /// Number of configurations
+    pub num_configurations: u8,
+}
+
+impl DeviceDescriptor {
+    fn parse(buf: &[u8; 18]) -> Self {
+        Self {
+            usb_release: u16::from_le_bytes([buf[2], buf[3]]),
+            device_class: buf[4],
+            device_subclass: buf[5],
+            device_protocol: buf[6],
+            max_packet_size_0: buf[7],
+            vendor_id: u16::from_le_bytes([buf[8], buf[9]]),
+            product_id: u16::from_le_bytes([buf[10], buf[11]]),
+            device_release: u16::from_le_bytes([buf[12], buf[13]]),
+            num_configurations: buf[17],
+        }
+    }
+}
+
+/// Handle to an interrupt IN endpoint opened with [`UsbHost::open_interrupt_in`]
+#[derive(Debug)]
+pub struct InterruptPipe {
+    /// Index of the interrupt endpoint slot in the controller, starting at 1
+    slot: usize,
+    max_packet_size: u16,
+}
+
+/// USB host driver
+pub struct UsbHost {
+    ctrl_reg: USBCTRL_REGS,
+    ctrl_dpram: USBCTRL_DPRAM,
+    address: u8,
+    max_packet_size_0: u16,
+    /// Next data PID of the bulk endpoints, one bit per endpoint number and direction
+    bulk_data1: [u16; 2],
+    /// Next data PID of the interrupt endpoint slots, one bit per slot
+    interrupt_data1: u16,
+    /// Interrupt endpoint slots in use, one bit per slot
+    interrupt_used: u16,
+    /// Number of NAKs after which a transaction on EPX is given up
+    nak_limit: Option<u32>,
+}
+
+impl UsbHost {
+    /// Create a new USB host driver and bring up the controller in host mode.
+    pub fn new(
+        ctrl_reg: USBCTRL_REGS,
+        ctrl_dpram: USBCTRL_DPRAM,
+        _pll: UsbClock,
+        resets: &mut RESETS,
+    ) -> Self {
+        ctrl_reg.reset_bring_down(resets);
+        ctrl_reg.reset_bring_up(resets);
+
+        unsafe {
+            let raw_ctrl_reg =
+                core::slice::from_raw_parts_mut(USBCTRL_REGS::ptr() as *mut u32, 1 + 0x98 / 4);
+            raw_ctrl_reg.fill(0);
+
+            let raw_ctrl_pdram =
+                core::slice::from_raw_parts_mut(USBCTRL_DPRAM::ptr() as *mut u32, 1 + 0xfc / 4);
+            raw_ctrl_pdram.fill(0);
+        }
+
+        ctrl_reg.usb_muxing.modify(|_, w| {
+            w.to_phy().set_bit();
+            w.softcon().set_bit()
+        });
+        ctrl_reg.usb_pwr.modify(|_, w| {
+            w.vbus_detect().set_bit();
+            w.vbus_detect_override_en().set_bit()
+        });
+        ctrl_reg.main_ctrl.modify(|_, w| {
+            w.sim_timing().clear_bit();
+            w.host_ndevice().set_bit();
+            w.controller_en().set_bit()
+        });
+        ctrl_reg.sie_ctrl.write(|w| {
+            w.sof_en().set_bit();
+            w.keep_alive_en().set_bit();
+            w.pulldown_en().set_bit();
+            w.ep0_int_1buf().set_bit()
+        });
+
+        Self {
+            ctrl_reg,
+            ctrl_dpram,
+            address: 0,
+            max_packet_size_0: 8,
+            bulk_data1: [0; 2],
+            interrupt_data1: 0,
+            interrupt_used: 0,
+            nak_limit: None,
+        }
+    }
+
+    /// Give up control and bulk transactions after the device answered `limit` times with NAK,
+    /// returning [`Error::Nak`]. With `None`, the default, the transactions are retried until
+    /// the device answers with data or an error.
+    ///
+    /// The controller retries on its own, so NAKs which occur in quick succession may be counted
+    /// once. The limit is therefore a lower bound of the number of retries.
+    pub fn set_nak_limit(&mut self, limit: Option<u32>) {
+        self.nak_limit = limit;
+    }
+
+    /// Free the underlying devices.
+    pub fn free(self) -> (USBCTRL_REGS, USBCTRL_DPRAM) {
+        (self.ctrl_reg, self.ctrl_dpram)
+    }
+
+    /// Speed of the connected device, or `None` if no device is connected.
+    pub fn speed(&self) -> Option<Speed> {
+        match self.ctrl_reg.sie_status.read().speed().bits() {
+            1 => Some(Speed::Low),
+            2 => Some(Speed::Full),
+            _ => None,
+        }
+    }
+
+    /// Check whether a device is connected.
+    pub fn is_connected(&self) -> bool {
+        self.speed().is_some()
+    }
+
+    /// Start a bus reset. The controller ends it on its own.
+    pub fn reset_bus(&mut self) {
+        self.ctrl_reg
+            .sie_ctrl
+            .modify(|_, w| w.reset_bus().set_bit());
+    }
+
+    /// Current frame number, as sent in the start of frame packets.
+    pub fn frame_number(&self) -> u16 {
+        self.ctrl_reg.sof_rd.read().count().bits()
+    }
+
+    /// Enumerate the connected device, assigning it an address and reading its device
+    /// descriptor.
+    ///
+    /// This closes all interrupt endpoints. The device is not configured yet, see
+    /// [`set_configuration`](Self::set_configuration).
+    pub fn enumerate<D: DelayMs<u32>>(&mut self, delay: &mut D) -> Result<DeviceDescriptor, Error> {
+        if !self.is_connected() {
+            return Err(Error::Disconnected);
+        }
+        self.ctrl_reg.int_ep_ctrl.write(|w| unsafe { w.bits(0) });
+        self.interrupt_used = 0;
+        self.bulk_data1 = [0; 2];
+
+        // Give the device time to settle after connecting, then reset it.
+        delay.delay_ms(100);
+        self.reset_bus();
+        delay.delay_ms(50);
+
+        // Learn the maximum packet size of endpoint 0 from the first 8 bytes of the descriptor.
+        self.address = 0;
+        self.max_packet_size_0 = 8;
+        let mut buf = [0; 18];
+        self.control_in(
+            SetupPacket::get_descriptor(DESCRIPTOR_TYPE_DEVICE, 0, 8),
+            &mut buf[..8],
+        )?;
+        self.max_packet_size_0 = u16::from(buf[7]).clamp(8, BUFFER_SIZE as u16);
+
+        self.control_out(SetupPacket::set_address(DEVICE_ADDRESS), &[])?;
+        self.address = DEVICE_ADDRESS;
+        // SET_ADDRESS recovery interval
+        delay.delay_ms(2);
+
+        self.control_in(
+            SetupPacket::get_descriptor(DESCRIPTOR_TYPE_DEVICE, 0, 18),
+            &mut buf,
+        )?;
+        Ok(DeviceDescriptor::parse(&buf))
+    }
+
+    /// Select the configuration `value` of the device.
+    pub fn set_configuration(&mut self, value: u8) -> Result<(), Error> {
+        self.control_out(SetupPacket::set_configuration(value), &[])?;
+        self.bulk_data1 = [0; 2];
+        Ok(())
+    }
+
+    /// Perform a control transfer with a data stage from the device, returning the number of
+    /// bytes received into `buf`.
+    ///
+    /// At most `setup.length` bytes are requested.
+    pub fn control_in(&mut self, setup: SetupPacket, buf: &mut [u8]) -> Result<usize, Error> {
+        debug_assert!(setup.is_in());
+        self.send_setup(setup)?;
+
+        let len = usize::from(setup.length).min(buf.len());
+        let max_packet_size = usize::from(self.max_packet_size_0);
+        let mut received = 0;
+        let mut data1 = true;
+        while received < len {
+            let n = self.receive(
+                0,
+                EndpointType::Control,
+                self.max_packet_size_0,
+                data1,
+                &mut buf[received..len],
+            )?;
+            received += n;
+            data1 = !data1;
+            if n < max_packet_size {
+                break;
+            }
+        }
+
+        // Status stage
+        self.send(0, EndpointType::Control, true, &[])?;
+        Ok(received)
+    }
+
+    /// Perform a control transfer without data stage, or with a data stage to the device.
+    pub fn control_out(&mut self, setup: SetupPacket, data: &[u8]) -> Result<(), Error> {
+        debug_assert!(!setup.is_in());
+        let setup = SetupPacket {
+            length: data.len() as u16,
+            ..setup
+        };
+        self.send_setup(setup)?;
+
+        let mut data1 = true;
+        for chunk in data.chunks(usize::from(self.max_packet_size_0)) {
+            self.send(0, EndpointType::Control, data1, chunk)?;
+            data1 = !data1;
+        }
+
+        // Status stage
+        let mut status = [0; 0];
+        self.receive(
+            0,
+            EndpointType::Control,
+            self.max_packet_size_0,
+            true,
+            &mut status,
+        )?;
+        Ok(())
+    }
+
+    /// Receive one packet from the bulk IN endpoint `endpoint`.
+    ///
+    /// `max_packet_size` is taken from the endpoint descriptor, and must not exceed 64 bytes.
+    /// Returns the number of bytes received, which is less than `max_packet_size` at the end of a
+    /// transfer.
+    ///
+    /// If the packet does not fit in `buf`, it is dropped and `BufferOverflow` is returned. The
+    /// packet has been acknowledged already, so the next call receives the next packet.
+    pub fn bulk_in(
+        &mut self,
+        endpoint: u8,
+        max_packet_size: u16,
+        buf: &mut [u8],
+    ) -> Result<usize, Error> {
+        if max_packet_size == 0 || usize::from(max_packet_size) > BUFFER_SIZE {
+            return Err(Error::BufferOverflow);
+        }
+        let mask = 1 << (endpoint & 0xf);
+        let data1 = self.bulk_data1[0] & mask != 0;
+        let result = self.receive(endpoint, EndpointType::Bulk, max_packet_size, data1, buf);
+        if let Ok(_) | Err(Error::BufferOverflow) = result {
+            self.bulk_data1[0] ^= mask;
+        }
+        result
+    }
+
+    /// Send `data` to the bulk OUT endpoint `endpoint`, in packets of `max_packet_size` bytes.
+    ///
+    /// `max_packet_size` is taken from the endpoint descriptor, and must not exceed 64 bytes. If
+    /// the length of `data` is a multiple of `max_packet_size`, no zero length packet is sent.
+    pub fn bulk_out(
+        &mut self,
+        endpoint: u8,
+        max_packet_size: u16,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        if max_packet_size == 0 || usize::from(max_packet_size) > BUFFER_SIZE {
+            return Err(Error::BufferOverflow);
+        }
+        let mask = 1 << (endpoint & 0xf);
+        for chunk in data.chunks(usize::from(max_packet_size)) {
+            let data1 = self.bulk_data1[1] & mask != 0;
+            self.send(endpoint, EndpointType::Bulk, data1, chunk)?;
+            self.bulk_data1[1] ^= mask;
+        }
+        Ok(())
+    }
+
+    /// Start polling the interrupt IN endpoint `endpoint` every `interval` milliseconds.
+    ///
+    /// `max_packet_size` is taken from the endpoint descriptor, and must not exceed 64 bytes.
+    pub fn open_interrupt_in(
+        &mut self,
+        endpoint: u8,
+        max_packet_size: u16,
+        interval: u8,
+    ) -> Result<InterruptPipe, Error> {
+        if usize::from(max_packet_size) > BUFFER_SIZE {
+            return Err(Error::BufferOverflow);
+        }
+        let slot = (1..=INTERRUPT_ENDPOINTS)
+            .find(|slot| self.interrupt_used & (1 << slot) == 0)
+            .ok_or(Error::NoFreeEndpoint)?;
+        self.interrupt_used |= 1 << slot;
+        self.interrupt_data1 &= !(1 << slot);
+
+        // Safety: The ADDR_ENDPx registers follow ADDR_ENDP, and the slot is owned by us.
+        unsafe {
+            let addr_endp = (USBCTRL_REGS::ptr() as *mut u32).add(slot);
+            write_volatile(
+                addr_endp,
+                u32::from(self.address) | (u32::from(endpoint & 0xf) << ADDR_ENDP_ENDPOINT_SHIFT),
+            );
+            write_volatile(
+                dpram_register(INT_EP_CTRL_OFFSET + 8 * (slot - 1)),
+                EP_CTRL_ENABLE
+                    | EP_CTRL_INTERRUPT_PER_BUFF
+                    | ((EndpointType::Interrupt as u32) << EP_CTRL_TYPE_SHIFT)
+                    | (u32::from(interval.max(1) - 1) << EP_CTRL_INTERVAL_SHIFT)
+                    | buffer_offset(slot) as u32,
+            );
+        }
+        let pipe = InterruptPipe {
+            slot,
+            max_packet_size,
+        };
+        self.arm_interrupt(&pipe);
+        self.ctrl_reg
+            .int_ep_ctrl
+            .modify(|r, w| unsafe { w.bits(r.bits() | (1 << slot)) });
+        Ok(pipe)
+    }
+
+    /// Stop polling an interrupt endpoint.
+    pub fn close_interrupt(&mut self, pipe: InterruptPipe) {
+        self.ctrl_reg
+            .int_ep_ctrl
+            .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << pipe.slot)) });
+        // Safety: The slot is owned by us.
+        unsafe {
+            write_volatile(dpram_register(INT_EP_CTRL_OFFSET + 8 * (pipe.slot - 1)), 0);
+        }
+        self.interrupt_used &= !(1 << pipe.slot);
+    }
+
+    /// Read the data received on an interrupt endpoint, if any.
+    ///
+    /// Returns the number of bytes received, or `WouldBlock` if the device did not send any
+    /// data since the last call.
+    pub fn read_interrupt(
+        &mut self,
+        pipe: &InterruptPipe,
+        buf: &mut [u8],
+    ) -> nb::Result<usize, Error> {
+        if !self.is_connected() {
+            return Err(nb::Error::Other(Error::Disconnected));
+        }
+        let mask = 1 << (pipe.slot * 2);
+        if self.ctrl_reg.buff_status.read().bits() & mask == 0 {
+            return Err(nb::Error::WouldBlock);
+        }
+        self.ctrl_reg.buff_status.write(|w| unsafe { w.bits(mask) });
+
+        // Safety: The buffer belongs to this slot, and has been released by the controller.
+        let len = unsafe {
+            let buf_ctrl =
+                read_volatile(dpram_register(INT_EP_BUF_CTRL_OFFSET + 8 * (pipe.slot - 1)));
+            let len = (buf_ctrl & BUF_CTRL_LENGTH_MASK) as usize;
+            if len > buf.len() {
+                self.rearm_interrupt(pipe);
+                return Err(nb::Error::Other(Error::BufferOverflow));
+            }
+            read_buffer(buffer_offset(pipe.slot), &mut buf[..len]);
+            len
+        };
+        self.rearm_interrupt(pipe);
+        Ok(len)
+    }
+
+    /// Toggle the data PID of an interrupt endpoint and hand its buffer back to the controller.
+    fn rearm_interrupt(&mut self, pipe: &InterruptPipe) {
+        self.interrupt_data1 ^= 1 << pipe.slot;
+        self.arm_interrupt(pipe);
+    }
+
+    fn arm_interrupt(&mut self, pipe: &InterruptPipe) {
+        let mut buf_ctrl = BUF_CTRL_LAST | u32::from(pipe.max_packet_size);
+        if self.interrupt_data1 & (1 << pipe.slot) != 0 {
+            buf_ctrl |= BUF_CTRL_DATA1;
+        }
+        // Safety: The buffer control register belongs to this slot.
+        unsafe {
+            arm_buffer(
+                dpram_register(INT_EP_BUF_CTRL_OFFSET + 8 * (pipe.slot - 1)),
+                buf_ctrl,
+            )
+        };
+    }
+
+    /// Send the setup packet of a control transfer.
+    fn send_setup(&mut self, setup: SetupPacket) -> Result<(), Error> {
+        // Safety: The setup packet is located at the start of the DPRAM.
+        unsafe { write_buffer(0, &setup.to_bytes()) };
+        self.configure_epx(0, EndpointType::Control);
+        self.start_transaction(|w| w.send_setup().set_bit())
+    }
+
+    /// Send one packet to `endpoint` using EPX.
+    fn send(
+        &mut self,
+        endpoint: u8,
+        ep_type: EndpointType,
+        data1: bool,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        debug_assert!(data.len() <= BUFFER_SIZE);
+        self.configure_epx(endpoint, ep_type);
+        let mut buf_ctrl = BUF_CTRL_FULL | BUF_CTRL_LAST | data.len() as u32;
+        if data1 {
+            buf_ctrl |= BUF_CTRL_DATA1;
+        }
+        // Safety: EPX is only used by the synchronous transfers of this driver.
+        unsafe {
+            write_buffer(DATA_OFFSET, data);
+            arm_buffer(dpram_register(EPX_BUF_CTRL_OFFSET), buf_ctrl);
+        }
+        self.start_transaction(|w| w.send_data().set_bit())
+    }
+
+    /// Receive one packet of up to `max_packet_size` bytes from `endpoint` using EPX, returning
+    /// its length.
+    fn receive(
+        &mut self,
+        endpoint: u8,
+        ep_type: EndpointType,
+        max_packet_size: u16,
+        data1: bool,
+        buf: &mut [u8],
+    ) -> Result<usize, Error> {
+        self.configure_epx(endpoint, ep_type);
+        let mut buf_ctrl = BUF_CTRL_LAST | u32::from(max_packet_size);
+        if data1 {
+            buf_ctrl |= BUF_CTRL_DATA1;
+        }
+        // Safety: EPX is only used by the synchronous transfers of this driver.
+        unsafe { arm_buffer(dpram_register(EPX_BUF_CTRL_OFFSET), buf_ctrl) };
+        self.start_transaction(|w| w.receive_data().set_bit())?;
+
+        // Safety: The transaction is complete, so the controller released the buffer.
+        unsafe {
+            let len = (read_volatile(dpram_register(EPX_BUF_CTRL_OFFSET)) & BUF_CTRL_LENGTH_MASK)
+                as usize;
+            if len > buf.len() {
+                return Err(Error::BufferOverflow);
+            }
+            read_buffer(DATA_OFFSET, &mut buf[..len]);
+            Ok(len)
+        }
+    }
+
+    /// Point EPX at `endpoint` of the device.
+    fn configure_epx(&mut self, endpoint: u8, ep_type: EndpointType) {
+        let address = self.address;
+        self.ctrl_reg.addr_endp.write(|w| unsafe {
+            w.address().bits(address);
+            w.endpoint().bits(endpoint & 0xf)
+        });
+        // Safety: EPX is only used by the synchronous transfers of this driver.
+        unsafe {
+            write_volatile(
+                dpram_register(EPX_CTRL_OFFSET),
+                EP_CTRL_ENABLE
+                    | EP_CTRL_INTERRUPT_PER_BUFF
+                    | ((ep_type as u32) << EP_CTRL_TYPE_SHIFT)
+                    | DATA_OFFSET as u32,
+            );
+        }
+    }
+
+    /// Start a transaction on EPX and wait for it to complete.
+    fn start_transaction(
+        &mut self,
+        direction: impl FnOnce(
+            &mut pac::usbctrl_regs::sie_ctrl::W,
+        ) -> &mut pac::usbctrl_regs::sie_ctrl::W,
+    ) -> Result<(), Error> {
+        // Clear the status flags of the previous transaction.
+        self.ctrl_reg.sie_status.write(|w| {
+            w.nak_rec().set_bit();
+            w.trans_complete().set_bit();
+            w.stall_rec().set_bit();
+            w.rx_timeout().set_bit();
+            w.data_seq_error().set_bit()
+        });
+
+        // The controller requires the START_TRANS bit to be set separately, a few cycles after
+        // the other bits.
+        self.ctrl_reg.sie_ctrl.modify(|_, w| direction(w));
+        cortex_m::asm::delay(12);
+        self.ctrl_reg
+            .sie_ctrl
+            .modify(|_, w| w.start_trans().set_bit());
+
+        let mut naks: u32 = 0;
+        let result = loop {
+            let status = self.ctrl_reg.sie_status.read();
+            if status.nak_rec().bit_is_set() {
+                self.ctrl_reg.sie_status.write(|w| w.nak_rec().set_bit());
+                naks += 1;
+                if self.nak_limit.map_or(false, |limit| naks >= limit) {
+                    break Err(Error::Nak);
+                }
+            }
+            if status.stall_rec().bit_is_set() {
+                break Err(Error::Stall);
+            } else if status.rx_timeout().bit_is_set() {
+                break Err(Error::Timeout);
+            } else if status.data_seq_error().bit_is_set() {
+                break Err(Error::DataSequence);
+            } else if status.trans_complete().bit_is_set() {
+                break Ok(());
+            } else if status.speed().bits() == 0 {
+                break Err(Error::Disconnected);
+            }
+        };
+        if result.is_err() {
+            self.ctrl_reg
+                .sie_ctrl
+                .modify(|_, w| w.stop_trans().set_bit());
+        }
+        self.ctrl_reg.sie_ctrl.modify(|_, w| {
+            w.send_setup().clear_bit();
+            w.send_data().clear_bit();
+            w.receive_data().clear_bit()
+        });
+        result
+    }
+}
+
+/// Pointer to the register at `offset` in the DPRAM.
+fn dpram_register(offset: usize) -> *mut u32 {
+    (USBCTRL_DPRAM::ptr() as usize + offset) as *mut u32
+}
+
+/// Offset of the data buffer used by interrupt endpoint `slot`.
+fn buffer_offset(slot: usize) -> usize {
+    DATA_OFFSET + slot * BUFFER_SIZE
+}
+
+/// Hand a buffer to the controller. The AVAILABLE bit has to be set a few cycles after the
+/// other bits.
+unsafe fn arm_buffer(buf_ctrl: *mut u32, value: u32) {
+    write_volatile(buf_ctrl, value);
+    cortex_m::asm::delay(12);
+    write_volatile(buf_ctrl, value | BUF_CTRL_AVAILABLE);
+}
+
+/// Copy `data` into the DPRAM at `offset`.
+unsafe fn write_buffer(offset: usize, data: &[u8]) {
+    let base = (USBCTRL_DPRAM::ptr() as usize + offset) as *mut u8;
+    core::slice::from_raw_parts_mut(base, data.len()).copy_from_slice(data);
+}
+
+/// Copy data from the DPRAM at `offset` into `buf`.
+unsafe fn read_buffer(offset: usize, buf: &mut [u8]) {
+    let base = (USBCTRL_DPRAM::ptr() as usize + offset) as *const u8;
+    buf.copy_from_slice(core::slice::from_raw_parts(base, buf.len()));
+}
@@ -0,0 +1,262 @@
+//! Core voltage regulator and brown-out detector (VREG_AND_CHIP_RESET)
+//!
+//! The core voltage defaults to 1.10V, which is specified for `clk_sys` up to 133MHz. Higher
+//! system clocks usually need a higher core voltage. [`setup_pll_sys_blocking`] raises the
+//! voltage as needed before starting the system PLL:
+//!
+//! ```no_run
+//! use embedded_time::rate::*;
+//! use rp2040_hal::{clocks::{ClocksManager, InitError}, pac, pll::PLLConfig, vreg::{self, Vreg}, xosc::setup_xosc_blocking};
+//!
+//! # fn func() -> Result<(), InitError> {
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! const XOSC_CRYSTAL_FREQ: u32 = 12_000_000; // Typically found in BSP crates
+//!
+//! // PLL SYS: 12 / 1 = 12MHz * 125 = 1500MHZ / 6 / 1 = 250MHz
+//! const PLL_SYS_250MHZ: PLLConfig<Megahertz> = PLLConfig {
+//!     vco_freq: Megahertz(1500),
+//!     refdiv: 1,
+//!     post_div1: 6,
+//!     post_div2: 1,
+//! };
+//!
+//! let mut vreg = Vreg::new(peripherals.VREG_AND_CHIP_RESET);
+//! let mut clocks = ClocksManager::new(peripherals.CLOCKS);
+//! let xosc = setup_xosc_blocking(peripherals.XOSC, XOSC_CRYSTAL_FREQ.Hz()).map_err(InitError::XoscErr)?;
+//! let pll_sys = vreg::setup_pll_sys_blocking(&mut vreg, peripherals.PLL_SYS, xosc.operating_frequency().into(), PLL_SYS_250MHZ, &mut clocks, &mut peripherals.RESETS).map_err(InitError::PllError)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The flash is clocked from `clk_sys` as well. The SSI divider set by the second stage
+//! bootloader has to keep it within the limits of the flash chip.
+//!
+//! See [Chapter 2 Section 10](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) for more details
+
+use crate::clocks::ClocksManager;
+use crate::pll::{start_pll_blocking, Error as PllError, Locked, PLLConfig, PhaseLockedLoop};
+use core::convert::TryFrom;
+use embedded_time::{
+    fixed_point::FixedPoint,
+    rate::{Generic, Hertz, Rate},
+};
+use pac::{PLL_SYS, RESETS, VREG_AND_CHIP_RESET};
+
+/// Output voltage of the core voltage regulator
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum VregVoltage {
+    /// 0.85V
+    V0_85 = 0b0110,
+    /// 0.90V
+    V0_90 = 0b0111,
+    /// 0.95V
+    V0_95 = 0b1000,
+    /// 1.00V
+    V1_00 = 0b1001,
+    /// 1.05V
+    V1_05 = 0b1010,
+    /// 1.10V, the default
+    V1_10 = 0b1011,
+    /// 1.15V
+    V1_15 = 0b1100,
+    /// 1.20V
+    V1_20 = 0b1101,
+    /// 1.25V
+    V1_25 = 0b1110,
+    /// 1.30V
+    V1_30 = 0b1111,
+}
+
+impl VregVoltage {
+    /// Core voltage commonly used to run `clk_sys` at `frequency`.
+    ///
+    /// Only up to 133MHz at 1.10V is covered by the datasheet. The higher voltages are what is
+    /// typically needed to overclock, individual chips may need more or less.
+    pub fn for_frequency(frequency: Hertz) -> Self {
+        match frequency.integer() {
+            0..=133_000_000 => VregVoltage::V1_10,
+            133_000_001..=200_000_000 => VregVoltage::V1_15,
+            200_000_001..=250_000_000 => VregVoltage::V1_20,
+            250_000_001..=300_000_000 => VregVoltage::V1_25,
+            _ => VregVoltage::V1_30,
+        }
+    }
+
+    fn from_vsel(vsel: u32) -> Option<Self> {
+        Some(match vsel {
+            0b0110 => VregVoltage::V0_85,
+            0b0111 => VregVoltage::V0_90,
+            0b1000 => VregVoltage::V0_95,
+            0b1001 => VregVoltage::V1_00,
+            0b1010 => VregVoltage::V1_05,
+            0b1011 => VregVoltage::V1_10,
+            0b1100 => VregVoltage::V1_15,
+            0b1101 => VregVoltage::V1_20,
+            0b1110 => VregVoltage::V1_25,
+            0b1111 => VregVoltage::V1_30,
+            _ => return None,
+        })
+    }
+}
+
+/// Threshold of the brown-out detector. The chip is reset when the core voltage drops below it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BodThreshold {
+    /// 0.473V
+    V0_473 = 0b0000,
+    /// 0.516V
+    V0_516 = 0b0001,
+    /// 0.559V
+    V0_559 = 0b0010,
+    /// 0.602V
+    V0_602 = 0b0011,
+    /// 0.645V
+    V0_645 = 0b0100,
+    /// 0.688V
+    V0_688 = 0b0101,
+    /// 0.731V
+    V0_731 = 0b0110,
+    /// 0.774V
+    V0_774 = 0b0111,
+    /// 0.817V
+    V0_817 = 0b1000,
+    /// 0.860V, the default
+    V0_860 = 0b1001,
+    /// 0.903V
+    V0_903 = 0b1010,
+    /// 0.946V
+    V0_946 = 0b1011,
+    /// 0.989V
+    V0_989 = 0b1100,
+    /// 1.032V
+    V1_032 = 0b1101,
+    /// 1.075V
+    V1_075 = 0b1110,
+    /// 1.118V
+    V1_118 = 0b1111,
+}
+
+/// `VSEL` field of the `VREG` and `BOD` registers
+const VSEL_MASK: u32 = 0xf << 4;
+/// `ROK` bit of the `VREG` register
+const VREG_ROK: u32 = 1 << 12;
+/// `EN` bit of the `BOD` register
+const BOD_EN: u32 = 1;
+
+/// Core voltage regulator and brown-out detector
+pub struct Vreg {
+    device: VREG_AND_CHIP_RESET,
+}
+
+impl Vreg {
+    /// Create a new voltage regulator driver.
+    pub fn new(device: VREG_AND_CHIP_RESET) -> Self {
+        Self { device }
+    }
+
+    /// Releases the underlying device.
+    pub fn free(self) -> VREG_AND_CHIP_RESET {
+        self.device
+    }
+
+    /// Current output voltage, or `None` if it was set below 0.85V.
+    pub fn voltage(&self) -> Option<VregVoltage> {
+        VregVoltage::from_vsel((self.device.vreg.read().bits() & VSEL_MASK) >> 4)
+    }
+
+    /// Set the output voltage and wait until it is in regulation.
+    ///
+    /// The brown-out detector resets the chip if the voltage drops below its threshold, which
+    /// is 0.86V by default. Lower the threshold before going below 0.90V.
+    pub fn set_voltage(&mut self, voltage: VregVoltage) {
+        self.device
+            .vreg
+            .modify(|r, w| unsafe { w.bits((r.bits() & !VSEL_MASK) | ((voltage as u32) << 4)) });
+        while !self.is_regulation_ok() {
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Raise the output voltage to [`VregVoltage::for_frequency`], if it is lower. The voltage
+    /// is never lowered.
+    pub fn raise_voltage_for(&mut self, frequency: Hertz) {
+        let voltage = VregVoltage::for_frequency(frequency);
+        if self.voltage().map_or(true, |current| current < voltage) {
+            self.set_voltage(voltage);
+        }
+    }
+
+    /// Check if the output voltage is in regulation.
+    pub fn is_regulation_ok(&self) -> bool {
+        self.device.vreg.read().bits() & VREG_ROK != 0
+    }
+
+    /// Enable the brown-out detector with the given threshold.
+    pub fn enable_brown_out_detection(&mut self, threshold: BodThreshold) {
+        self.device
+            .bod
+            .write(|w| unsafe { w.bits(((threshold as u32) << 4) | BOD_EN) });
+    }
+
+    /// Disable the brown-out detector.
+    pub fn disable_brown_out_detection(&mut self) {
+        self.device
+            .bod
+            .modify(|r, w| unsafe { w.bits(r.bits() & !BOD_EN) });
+    }
+
+    /// Threshold of the brown-out detector, or `None` if it is disabled.
+    pub fn brown_out_threshold(&self) -> Option<BodThreshold> {
+        use BodThreshold::*;
+        const THRESHOLDS: [BodThreshold; 16] = [
+            V0_473, V0_516, V0_559, V0_602, V0_645, V0_688, V0_731, V0_774, V0_817, V0_860, V0_903,
+            V0_946, V0_989, V1_032, V1_075, V1_118,
+        ];
+        let bod = self.device.bod.read().bits();
+        if bod & BOD_EN == 0 {
+            return None;
+        }
+        Some(THRESHOLDS[((bod & VSEL_MASK) >> 4) as usize])
+    }
+}
+
+/// Set up the system PLL like [`setup_pll_blocking`](crate::pll::setup_pll_blocking), raising
+/// the core voltage first if the resulting frequency needs it. See [`Vreg::raise_voltage_for`].
+///
+/// The configuration is validated before the voltage is changed.
+pub fn setup_pll_sys_blocking<R: Rate>(
+    vreg: &mut Vreg,
+    dev: PLL_SYS,
+    xosc_frequency: Generic<u32>,
+    config: PLLConfig<R>,
+    clocks: &mut ClocksManager,
+    resets: &mut RESETS,
+) -> Result<PhaseLockedLoop<Locked, PLL_SYS>, PllError>
+where
+    R: Into<Hertz<u64>>,
+{
+    let PLLConfig {
+        vco_freq,
+        refdiv,
+        post_div1,
+        post_div2,
+    } = config;
+    let vco_freq: Hertz<u64> = vco_freq.into();
+    let config = PLLConfig {
+        vco_freq,
+        refdiv,
+        post_div1,
+        post_div2,
+    };
+    let pll = PhaseLockedLoop::new(dev, xosc_frequency, config)?;
+
+    // The post dividers are non-zero, as the configuration is valid.
+    let post_div = u64::from(post_div1) * u64::from(post_div2);
+    let frequency =
+        u32::try_from(vco_freq.integer() / post_div).map_err(|_| PllError::BadArgument)?;
+    vreg.raise_voltage_for(Hertz(frequency));
+
+    Ok(start_pll_blocking(pll, clocks, resets))
+}
@@ -33,11 +33,46 @@
 //! loop {}
 //! ```
 //! See [examples/watchdog.rs](https://github.com/rp-rs/rp-hal/tree/main/rp2040-hal/examples/watchdog.rs) for a more complete example
+//!
+//! The eight scratch registers keep their contents across watchdog resets, e.g. for a crash counter
+//! with [`Watchdog::read_scratch`] and [`Watchdog::write_scratch`]. [`Watchdog::reboot`] resets
+//! the chip right away, and [`Watchdog::reboot_to`] makes the boot ROM start a given entry point.
 
 use crate::pac::WATCHDOG;
 use embedded_hal::watchdog;
 use embedded_time::{duration, fixed_point::FixedPoint};
 
+/// One of the eight watchdog scratch registers, which keep their contents across resets
+/// except for a power-on reset, a brown-out or the RUN pin.
+///
+/// The boot ROM uses [`Scratch4`](ScratchRegister::Scratch4) to
+/// [`Scratch7`](ScratchRegister::Scratch7) to decide where to start after a watchdog reset, see
+/// [`Watchdog::reboot_to`]. Writing [`Scratch4`](ScratchRegister::Scratch4) with anything but
+/// zero is not recommended.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ScratchRegister {
+    /// Scratch register 0
+    Scratch0,
+    /// Scratch register 1
+    Scratch1,
+    /// Scratch register 2
+    Scratch2,
+    /// Scratch register 3
+    Scratch3,
+    /// Scratch register 4
+    Scratch4,
+    /// Scratch register 5
+    Scratch5,
+    /// Scratch register 6
+    Scratch6,
+    /// Scratch register 7
+    Scratch7,
+}
+
+/// Value of scratch register 4 which makes the boot ROM start from scratch register 7
+const BOOT_MAGIC: u32 = 0xb007c0d3;
+
 /// Watchdog peripheral
 pub struct Watchdog {
     watchdog: WATCHDOG,
@@ -83,6 +118,70 @@ impl Watchdog {
         })
     }
 
+    /// Read a scratch register.
+    pub fn read_scratch(&self, register: ScratchRegister) -> u32 {
+        match register {
+            ScratchRegister::Scratch0 => self.watchdog.scratch0.read().bits(),
+            ScratchRegister::Scratch1 => self.watchdog.scratch1.read().bits(),
+            ScratchRegister::Scratch2 => self.watchdog.scratch2.read().bits(),
+            ScratchRegister::Scratch3 => self.watchdog.scratch3.read().bits(),
+            ScratchRegister::Scratch4 => self.watchdog.scratch4.read().bits(),
+            ScratchRegister::Scratch5 => self.watchdog.scratch5.read().bits(),
+            ScratchRegister::Scratch6 => self.watchdog.scratch6.read().bits(),
+            ScratchRegister::Scratch7 => self.watchdog.scratch7.read().bits(),
+        }
+    }
+
+    /// Write a scratch register. The value is kept across watchdog and soft resets.
+    pub fn write_scratch(&mut self, register: ScratchRegister, value: u32) {
+        match register {
+            ScratchRegister::Scratch0 => self.watchdog.scratch0.write(|w| unsafe { w.bits(value) }),
+            ScratchRegister::Scratch1 => self.watchdog.scratch1.write(|w| unsafe { w.bits(value) }),
+            ScratchRegister::Scratch2 => self.watchdog.scratch2.write(|w| unsafe { w.bits(value) }),
+            ScratchRegister::Scratch3 => self.watchdog.scratch3.write(|w| unsafe { w.bits(value) }),
+            ScratchRegister::Scratch4 => self.watchdog.scratch4.write(|w| unsafe { w.bits(value) }),
+            ScratchRegister::Scratch5 => self.watchdog.scratch5.write(|w| unsafe { w.bits(value) }),
+            ScratchRegister::Scratch6 => self.watchdog.scratch6.write(|w| unsafe { w.bits(value) }),
+            ScratchRegister::Scratch7 => self.watchdog.scratch7.write(|w| unsafe { w.bits(value) }),
+        }
+    }
+
+    /// Reset the chip immediately, and boot normally.
+    pub fn reboot(&mut self) -> ! {
+        // Make sure the boot ROM ignores a target left by `reboot_to`.
+        self.write_scratch(ScratchRegister::Scratch4, 0);
+        self.trigger()
+    }
+
+    /// Reset the chip immediately, and let the boot ROM start the code at `entry_pc` with the
+    /// stack pointer set to `sp`.
+    ///
+    /// The target is started before the flash is set up for execute-in-place. It has to be in
+    /// RAM, or set up the flash itself like a second stage bootloader. The boot ROM only
+    /// starts it once, the next reset boots normally.
+    pub fn reboot_to(&mut self, entry_pc: u32, sp: u32) -> ! {
+        // The boot ROM jumps to the target in thumb mode.
+        let entry_pc = entry_pc | 1;
+        self.write_scratch(ScratchRegister::Scratch4, BOOT_MAGIC);
+        self.write_scratch(
+            ScratchRegister::Scratch5,
+            entry_pc ^ BOOT_MAGIC.wrapping_neg(),
+        );
+        self.write_scratch(ScratchRegister::Scratch6, sp);
+        self.write_scratch(ScratchRegister::Scratch7, entry_pc);
+        self.trigger()
+    }
+
+    fn trigger(&mut self) -> ! {
+        unsafe {
+            self.configure_wdog_reset_triggers();
+        }
+        self.watchdog.ctrl.write(|w| w.trigger().set_bit());
+        loop {
+            cortex_m::asm::nop();
+        }
+    }
+
     fn load_counter(&self, counter: u32) {
         self.watchdog.load.write(|w| unsafe { w.bits(counter) });
     }